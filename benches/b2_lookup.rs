@@ -0,0 +1,72 @@
+// Compares approaches to looking up table B.2 mappings.
+//
+// Run with `cargo bench --bench b2_lookup`.
+extern crate stringprep;
+
+use std::char;
+use std::time::Instant;
+use stringprep::tables;
+
+const ITERATIONS: usize = 100;
+const FLAT_LIMIT: u32 = 0x0590;
+
+// A flat array for the low code points, falling back to the binary search.
+struct TwoLevel {
+    flat: Vec<Option<&'static str>>,
+}
+
+impl TwoLevel {
+    fn new() -> TwoLevel {
+        let flat = (0..FLAT_LIMIT)
+            .map(|i| char::from_u32(i).and_then(tables::b2_lookup_binary))
+            .collect();
+        TwoLevel { flat }
+    }
+
+    fn lookup(&self, c: char) -> Option<&'static str> {
+        if (c as u32) < FLAT_LIMIT {
+            self.flat[c as usize]
+        } else {
+            tables::b2_lookup_binary(c)
+        }
+    }
+}
+
+fn bench<F>(name: &str, input: &[char], f: F)
+where
+    F: Fn(char) -> Option<&'static str>,
+{
+    let start = Instant::now();
+    let mut hits = 0;
+    for _ in 0..ITERATIONS {
+        for &c in input {
+            if f(c).is_some() {
+                hits += 1;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    let per_char = elapsed / (ITERATIONS * input.len()) as u32;
+    println!(
+        "{:<16} {:>10?} total, {:>6?}/char ({} hits)",
+        name, elapsed, per_char, hits
+    );
+}
+
+fn main() {
+    let inputs: &[(&str, Vec<char>)] = &[
+        ("ascii", (0..0x80).filter_map(char::from_u32).collect()),
+        (
+            "latin/greek",
+            (0x80..FLAT_LIMIT).filter_map(char::from_u32).collect(),
+        ),
+        ("bmp", (0..0x10000).filter_map(char::from_u32).collect()),
+    ];
+    let two_level = TwoLevel::new();
+
+    for &(name, ref input) in inputs {
+        println!("{}:", name);
+        bench("binary search", input, tables::b2_lookup_binary);
+        bench("two-level", input, |c| two_level.lookup(c));
+    }
+}
@@ -0,0 +1,63 @@
+// Compares checking two passwords for SASLprep equality with `saslprep_eq`
+// against preparing both with `saslprep_stored` and comparing the results.
+//
+// Run with `cargo bench --bench saslprep_eq`.
+extern crate stringprep;
+
+use std::time::Instant;
+
+const ITERATIONS: usize = 100_000;
+
+fn bench<F>(name: &str, pairs: &[(&str, &str)], f: F)
+where
+    F: Fn(&str, &str) -> bool,
+{
+    let start = Instant::now();
+    let mut matches = 0;
+    for _ in 0..ITERATIONS {
+        for &(a, b) in pairs {
+            if f(a, b) {
+                matches += 1;
+            }
+        }
+    }
+    let elapsed = start.elapsed();
+    let per_pair = elapsed / (ITERATIONS * pairs.len()) as u32;
+    println!(
+        "{:<16} {:>10?} total, {:>6?}/pair ({} matches)",
+        name, elapsed, per_pair, matches
+    );
+}
+
+fn main() {
+    let inputs: &[(&str, &[(&str, &str)])] = &[
+        (
+            "ascii",
+            &[
+                (
+                    "correct horse battery staple",
+                    "correct horse battery staple",
+                ),
+                ("hunter2", "hunter3"),
+            ],
+        ),
+        (
+            "non-ascii",
+            &[
+                ("p\u{00E4}ssw\u{00F6}rd", "pa\u{0308}ssw\u{00F6}rd"),
+                ("I\u{00AD}X\u{00A0}\u{2168}", "IX IX"),
+                ("\u{5BC6}\u{7801}\u{3000}\u{FF11}", "\u{5BC6}\u{7801} 1"),
+            ],
+        ),
+    ];
+
+    for &(name, pairs) in inputs {
+        println!("{}:", name);
+        bench("saslprep_stored", pairs, |a, b| {
+            stringprep::saslprep_stored(a).unwrap() == stringprep::saslprep_stored(b).unwrap()
+        });
+        bench("saslprep_eq", pairs, |a, b| {
+            stringprep::saslprep_eq(a, b).unwrap()
+        });
+    }
+}
@@ -0,0 +1,55 @@
+// Builds a custom stringprep profile out of the tables in `stringprep::tables`.
+//
+// The profile maps B.1 characters to nothing, case folds with B.2, normalizes
+// with NFKC, prohibits spaces and control characters and runs the
+// bidirectional text check. Unassigned code points are rejected as well.
+//
+// Run with `cargo run --example custom_profile -- <input>`.
+extern crate stringprep;
+
+use std::borrow::Cow;
+use std::env;
+use stringprep::{prepare, tables, Normalization, Profile};
+
+struct CustomPrep;
+
+impl Profile for CustomPrep {
+    fn map_char(&self, c: char) -> Option<char> {
+        let mut mapped = tables::map_chars_nameprep(c);
+        match (mapped.next(), mapped.next()) {
+            (m, None) => m,
+            _ => Some(c),
+        }
+    }
+
+    // B.2 maps some characters to several characters.
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        Cow::Owned(s.chars().flat_map(tables::map_chars_nameprep).collect())
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        tables::ascii_space_character(c)
+            || tables::non_ascii_space_character(c)
+            || tables::ascii_control_character(c)
+            || tables::non_ascii_control_character(c)
+    }
+
+    fn normalization(&self) -> Normalization {
+        Normalization::Nfkc
+    }
+
+    fn check_bidi(&self) -> bool {
+        true
+    }
+}
+
+fn main() {
+    let input = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Stra\u{00DF}e\u{200B}".to_string());
+
+    match prepare(&input, &CustomPrep) {
+        Ok(prepared) => println!("{:?} => {:?}", input, prepared),
+        Err(e) => println!("{:?} was rejected: {}", input, e),
+    }
+}
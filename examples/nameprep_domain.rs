@@ -0,0 +1,26 @@
+// Prepares each label of an internationalized domain name with Nameprep.
+//
+// Run with `cargo run --example nameprep_domain -- <domain>`.
+extern crate stringprep;
+
+use std::env;
+use stringprep::tables;
+
+fn main() {
+    let domain = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "Räksmörgås\u{3002}Josefßon.ORG".to_string());
+
+    println!("input: {}", domain);
+    for label in domain.split(tables::is_idna_label_separator) {
+        match stringprep::nameprep(label) {
+            Ok(prepared) => println!("  {:?} => {:?}", label, prepared),
+            Err(e) => println!("  {:?} => error: {}", label, e),
+        }
+    }
+
+    match stringprep::nameprep_domain(&domain) {
+        Ok(prepared) => println!("prepared: {}", prepared),
+        Err(e) => println!("error: {}", e),
+    }
+}
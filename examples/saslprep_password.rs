@@ -0,0 +1,29 @@
+// Prepares a password for a PostgreSQL SCRAM-SHA-256 connection.
+//
+// Run with `cargo run --example saslprep_password -- <password>`.
+extern crate stringprep;
+
+use std::borrow::Cow;
+use std::env;
+
+fn main() {
+    let password = env::args()
+        .nth(1)
+        .unwrap_or_else(|| "I\u{00AD}X\u{00A0}\u{2168}".to_string());
+
+    // PostgreSQL falls back to the raw password if it is not valid for
+    // SASLprep, so do the same here.
+    let prepared = match stringprep::saslprep_stored(&password) {
+        Ok(prepared) => prepared,
+        Err(e) => {
+            println!(
+                "{:?} is not valid for SASLprep ({}), using it as-is",
+                password, e
+            );
+            Cow::Borrowed(&password[..])
+        }
+    };
+
+    println!("input:    {:?}", password);
+    println!("prepared: {:?}", prepared);
+}
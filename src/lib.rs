@@ -1,425 +1,3285 @@
 //! An implementation of the "stringprep" algorithm defined in [RFC 3454][].
 //!
 //! [RFC 3454]: https://tools.ietf.org/html/rfc3454
+//!
+//! The crate supports `no_std` environments with `alloc` when its default
+//! `std` feature is disabled. The `std` feature adds implementations of
+//! `std::error::Error` and `AsRef<Path>`.
 #![warn(missing_docs)]
+#![no_std]
+#[macro_use]
+extern crate alloc;
+#[cfg(any(feature = "std", test))]
+extern crate std;
+
+#[cfg(feature = "log")]
+extern crate log;
 extern crate unicode_bidi;
 extern crate unicode_normalization;
 extern crate unicode_properties;
 
-use std::borrow::Cow;
-use std::fmt;
-use unicode_normalization::UnicodeNormalization;
-use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::{self, Vec};
+use core::borrow::Borrow;
+use core::cmp::Ordering;
+use core::convert::TryFrom;
+use core::fmt;
+use core::hash::{Hash, Hasher};
+use core::marker::PhantomData;
+use core::ops::Deref;
+use core::str;
+#[cfg(feature = "std")]
+use std::path::Path;
+use unicode_bidi::{bidi_class, BidiClass};
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+// Emits a trace message when the `log` feature is enabled. Callers must not
+// log the strings being prepared, as they are frequently passwords.
+#[cfg(feature = "log")]
+macro_rules! trace {
+    ($($arg:tt)*) => {
+        ::log::trace!($($arg)*)
+    };
+}
+
+#[cfg(not(feature = "log"))]
+macro_rules! trace {
+    ($($arg:tt)*) => {};
+}
 
+mod punycode;
 mod rfc3454;
 pub mod tables;
 
 /// Describes why a string failed stringprep normalization.
-#[derive(Debug)]
-enum ErrorCause {
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum ErrorKind {
     /// Contains stringprep prohibited characters.
     ProhibitedCharacter(char),
+    /// Contains code points unassigned in Unicode 3.2.
+    UnassignedCodePoint(char),
     /// Violates stringprep rules for bidirectional text.
     ProhibitedBidirectionalText,
     /// Starts with a combining character
     StartsWithCombiningCharacter,
     /// Empty String
     EmptyString,
+    /// Output does not fit in the provided buffer
+    OutputTooLarge,
+    /// Consists entirely of space characters
+    AllSpaces,
+    /// Invalid or unrepresentable Punycode
+    InvalidPunycode,
+    /// Exceeds the maximum length, in bytes
+    TooLong(usize),
+    /// Does not reach a stable result when preparation is repeated
+    Unstable,
+    /// Is not a valid ASCII Compatible Encoding of a domain name label
+    InvalidAce,
 }
 
 /// An error performing the stringprep algorithm.
 #[derive(Debug)]
-pub struct Error(ErrorCause);
+pub struct Error(
+    ErrorKind,
+    // the RFC 3454 table prohibiting the character of a `ProhibitedCharacter`
+    // error, as recorded where the character was rejected
+    Option<&'static str>,
+);
+
+impl Error {
+    fn prohibited(c: char, table: Option<&'static str>) -> Error {
+        Error(ErrorKind::ProhibitedCharacter(c), table)
+    }
+
+    /// Returns the reason the string was rejected.
+    #[must_use]
+    pub fn kind(&self) -> &ErrorKind {
+        &self.0
+    }
+}
 
 impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
         match self.0 {
-            ErrorCause::ProhibitedCharacter(c) => write!(fmt, "prohibited character `{}`", c),
-            ErrorCause::ProhibitedBidirectionalText => write!(fmt, "prohibited bidirectional text"),
-            ErrorCause::StartsWithCombiningCharacter => {
+            ErrorKind::ProhibitedCharacter(c) => match self.1 {
+                Some(table) => write!(
+                    fmt,
+                    "prohibited character `{}` (RFC 3454 table {})",
+                    c, table
+                ),
+                None => write!(fmt, "prohibited character `{}`", c),
+            },
+            ErrorKind::UnassignedCodePoint(c) => write!(fmt, "unassigned code point `{}`", c),
+            ErrorKind::ProhibitedBidirectionalText => write!(fmt, "prohibited bidirectional text"),
+            ErrorKind::StartsWithCombiningCharacter => {
                 write!(fmt, "starts with combining character")
             }
-            ErrorCause::EmptyString => write!(fmt, "empty string"),
+            ErrorKind::EmptyString => write!(fmt, "empty string"),
+            ErrorKind::OutputTooLarge => write!(fmt, "output too large"),
+            ErrorKind::AllSpaces => write!(fmt, "consists entirely of spaces"),
+            ErrorKind::InvalidPunycode => write!(fmt, "invalid punycode"),
+            ErrorKind::TooLong(len) => write!(fmt, "too long ({} bytes)", len),
+            ErrorKind::Unstable => write!(fmt, "preparation does not reach a stable result"),
+            ErrorKind::InvalidAce => write!(fmt, "invalid ACE label"),
         }
     }
 }
 
+#[cfg(feature = "std")]
 impl std::error::Error for Error {}
 
 /// Prepares a string with the SASLprep profile of the stringprep algorithm.
 ///
-/// SASLprep is defined in [RFC 4013][].
+/// SASLprep is defined in [RFC 4013][]. This is an alias for
+/// `saslprep_stored`, so unassigned code points are rejected; use
+/// `saslprep_query` to allow them.
 ///
 /// [RFC 4013]: https://tools.ietf.org/html/rfc4013
+#[deprecated(note = "use saslprep_stored or saslprep_query")]
+#[must_use = "the prepared string must be used"]
 pub fn saslprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    saslprep_stored(s)
+}
+
+/// Prepares a stored string with the SASLprep profile of the stringprep
+/// algorithm.
+///
+/// As required for stored strings by [RFC 4013, Section 2.5][], an error is
+/// returned if the prepared string contains unassigned code points.
+///
+/// [RFC 4013, Section 2.5]: https://tools.ietf.org/html/rfc4013#section-2.5
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_stored(s: &str) -> Result<Cow<'_, str>, Error> {
     // fast path for ascii text
-    if s.chars()
-        .all(|c| c.is_ascii() && !tables::ascii_control_character(c))
-    {
+    trace!("saslprep: input length={}", s.len());
+    if saslprep_ascii_check(s) {
+        trace!("saslprep: input is printable ASCII");
         return Ok(Cow::Borrowed(s));
     }
 
-    // 2.1 Mapping
-    let mapped = s
-        .chars()
-        .map(|c| {
-            if tables::non_ascii_space_character(c) {
-                ' '
-            } else {
-                c
-            }
-        })
-        .filter(|&c| !tables::commonly_mapped_to_nothing(c));
-
-    // 2.2 Normalization
-    let normalized = mapped.nfkc().collect::<String>();
+    prepare(s, &SaslPrep)
+}
 
-    // 2.3 Prohibited Output
-    let prohibited = normalized.chars().find(|&c| {
-        tables::non_ascii_space_character(c) /* C.1.2 */ ||
-            tables::ascii_control_character(c) /* C.2.1 */ ||
-            tables::non_ascii_control_character(c) /* C.2.2 */ ||
-            tables::private_use(c) /* C.3 */ ||
-            tables::non_character_code_point(c) /* C.4 */ ||
-            tables::surrogate_code(c) /* C.5 */ ||
-            tables::inappropriate_for_plain_text(c) /* C.6 */ ||
-            tables::inappropriate_for_canonical_representation(c) /* C.7 */ ||
-            tables::change_display_properties_or_deprecated(c) /* C.8 */ ||
-            tables::tagging_character(c) /* C.9 */
-    });
-    if let Some(c) = prohibited {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+/// Prepares a query string with the SASLprep profile of the stringprep
+/// algorithm.
+///
+/// Unlike `saslprep_stored`, unassigned code points are allowed, as
+/// [RFC 4013, Section 2.5][] permits for queries.
+///
+/// [RFC 4013, Section 2.5]: https://tools.ietf.org/html/rfc4013#section-2.5
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_query(s: &str) -> Result<Cow<'_, str>, Error> {
+    // fast path for ascii text
+    trace!("saslprep: input length={}", s.len());
+    if saslprep_ascii_check(s) {
+        trace!("saslprep: input is printable ASCII");
+        return Ok(Cow::Borrowed(s));
     }
 
-    // 2.4. Bidirectional Characters
-    if is_prohibited_bidirectional_text(&normalized) {
-        return Err(Error(ErrorCause::ProhibitedBidirectionalText));
-    }
+    let profile = WithChecks {
+        profile: &SaslPrep,
+        check_bidi: true,
+        check_unassigned: false,
+    };
+    prepare(s, &profile)
+}
 
-    // 2.5 Unassigned Code Points
-    let unassigned = normalized
-        .chars()
-        .find(|&c| tables::unassigned_code_point(c));
-    if let Some(c) = unassigned {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+/// Determines if `s` is printable ASCII, and therefore unchanged by SASLprep.
+///
+/// If this returns `true`, `s` can be used directly; otherwise it must be
+/// prepared with `saslprep_stored` or `saslprep_query`.
+#[must_use = "the prohibition check result must be used"]
+pub fn saslprep_ascii_check(s: &str) -> bool {
+    tables::is_all_saslprep_ascii(s)
+}
+
+/// Prepares a string with SASLprep, rejecting results longer than
+/// `max_utf8_bytes` bytes.
+///
+/// The length is checked after preparation, since normalization can change it.
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_with_max_len(s: &str, max_utf8_bytes: usize) -> Result<Cow<'_, str>, Error> {
+    let prepared = saslprep_stored(s)?;
+    if prepared.len() > max_utf8_bytes {
+        return Err(Error(ErrorKind::TooLong(prepared.len()), None));
     }
+    Ok(prepared)
+}
 
-    Ok(Cow::Owned(normalized))
+/// Prepares a password with SASLprep for use with SCRAM.
+///
+/// [RFC 5802][] defines `Normalize(str)` as SASLprep treating `str` as a stored
+/// string, so unassigned code points are rejected. The UTF-8 bytes of the
+/// result (e.g. `as_bytes()`), not the original input and not any other
+/// encoding, are what must be passed as the password to `Hi()`, i.e. PBKDF2.
+///
+/// [RFC 5802]: https://tools.ietf.org/html/rfc5802
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_for_scram(password: &str) -> Result<Cow<'_, str>, Error> {
+    saslprep_stored(password)
 }
 
-// RFC3454, 6. Bidirectional Characters
-fn is_prohibited_bidirectional_text(s: &str) -> bool {
-    if s.contains(tables::bidi_r_or_al) {
-        // 2) If a string contains any RandALCat character, the string
-        // MUST NOT contain any LCat character.
-        if s.contains(tables::bidi_l) {
-            return true;
-        }
+/// Prepares a password with SASLprep for an OAuth 2.0 resource owner password
+/// credentials grant, as described in [RFC 6749, Section 4.3][], and encodes
+/// it for an `application/x-www-form-urlencoded` request body.
+///
+/// ASCII alphanumerics and `*`, `-`, `.` and `_` are unchanged, spaces are
+/// encoded as `+`, and all other bytes of the UTF-8 encoding of the prepared
+/// password are percent-encoded.
+///
+/// [RFC 6749, Section 4.3]: https://tools.ietf.org/html/rfc6749#section-4.3
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_for_oauth(password: &str) -> Result<Cow<'_, str>, Error> {
+    use core::fmt::Write;
 
-        // 3) If a string contains any RandALCat character, a RandALCat
-        // character MUST be the first character of the string, and a
-        // RandALCat character MUST be the last character of the string.
-        if !tables::bidi_r_or_al(s.chars().next().unwrap())
-            || !tables::bidi_r_or_al(s.chars().next_back().unwrap())
-        {
-            return true;
+    let prepared = saslprep_stored(password)?;
+    if prepared.bytes().all(is_form_urlencoded_unreserved) {
+        return Ok(prepared);
+    }
+
+    let mut out = String::with_capacity(prepared.len() * 3);
+    for b in prepared.bytes() {
+        if is_form_urlencoded_unreserved(b) {
+            out.push(b as char);
+        } else if b == b' ' {
+            out.push('+');
+        } else {
+            write!(out, "%{:02X}", b).unwrap();
         }
     }
+    Ok(Cow::Owned(out))
+}
 
-    false
+fn is_form_urlencoded_unreserved(b: u8) -> bool {
+    b.is_ascii_alphanumeric() || matches!(b, b'*' | b'-' | b'.' | b'_')
 }
 
-/// Prepares a string with the Nameprep profile of the stringprep algorithm.
+/// Prepares a user-id or password with SASLprep for HTTP Basic
+/// authentication, as described in [RFC 7617][].
 ///
-/// Nameprep is defined in [RFC 3491][].
+/// [RFC 7617]: https://tools.ietf.org/html/rfc7617
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_for_basic_auth(credential: &str) -> Result<Cow<'_, str>, Error> {
+    saslprep_stored(credential)
+}
+
+/// Prepares a password with SASLprep for an LDAP simple or SASL `PLAIN` bind.
 ///
-/// [RFC 3491]: https://tools.ietf.org/html/rfc3491
-pub fn nameprep(s: &str) -> Result<Cow<'_, str>, Error> {
+/// This is the function to use to prepare credentials for `PLAIN`
+/// authentication, as described in [RFC 4616][]. In addition to the SASLprep
+/// checks, the prepared password must not be empty, so strings consisting only
+/// of characters mapped to nothing are rejected.
+///
+/// [RFC 4616]: https://tools.ietf.org/html/rfc4616
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_for_ldap_bind(s: &str) -> Result<Cow<'_, str>, Error> {
+    let prepared = saslprep_stored(s)?;
+    if prepared.is_empty() {
+        return Err(Error(ErrorKind::EmptyString, None));
+    }
+    Ok(prepared)
+}
+
+/// Determines if two strings are equal after being prepared with SASLprep.
+///
+/// An error is returned if either string is not valid for `saslprep_stored`.
+/// Unlike comparing the results of `saslprep_stored`, the prepared strings
+/// are compared as they are produced rather than being collected into new
+/// strings, so nothing is allocated unless normalization needs to buffer a
+/// long run of combining characters.
+#[must_use = "the comparison result must be used"]
+pub fn saslprep_eq(a: &str, b: &str) -> Result<bool, Error> {
     // fast path for ascii text
-    if s.chars()
-        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-')
-    {
-        return Ok(Cow::Borrowed(s));
+    if saslprep_ascii_check(a) && saslprep_ascii_check(b) {
+        return Ok(a == b);
     }
 
-    // 3. Mapping
-    let mapped = s
-        .chars()
-        .filter(|&c| !tables::commonly_mapped_to_nothing(c))
-        .flat_map(tables::case_fold_for_nfkc);
+    eq_chars(saslprep_chars(a), saslprep_chars(b), &SaslPrep)
+}
 
-    // 4. Normalization
-    let normalized = mapped.nfkc().collect::<String>();
+/// Prepares a string with SASLprep, returning every problem found with it.
+///
+/// Unlike `saslprep_stored`, which stops at the first error, every prohibited
+/// character and unassigned code point is reported, followed by the result of
+/// the bidirectional text check. An empty `Vec` means that the string is
+/// valid.
+#[must_use]
+pub fn saslprep_validate(s: &str) -> Vec<Error> {
+    validate_inner(s, &SaslPrep)
+}
 
-    // 5. Prohibited Output
-    let prohibited = normalized.chars().find(|&c| {
-        tables::non_ascii_space_character(c) /* C.1.2 */ ||
-            tables::non_ascii_control_character(c) /* C.2.2 */ ||
-            tables::private_use(c) /* C.3 */ ||
-            tables::non_character_code_point(c) /* C.4 */ ||
-            tables::surrogate_code(c) /* C.5 */ ||
-            tables::inappropriate_for_plain_text(c) /* C.6 */ ||
-            tables::inappropriate_for_canonical_representation(c) /* C.7 */ ||
-            tables::change_display_properties_or_deprecated(c) /* C.9 */ ||
-            tables::tagging_character(c) /* C.9 */
-    });
-    if let Some(c) = prohibited {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
-    }
+// RFC4013, 2.1 and 2.2, without collecting the result
+fn saslprep_chars(s: &str) -> impl Iterator<Item = char> + '_ {
+    s.chars().filter_map(tables::map_char_saslprep).nfkc()
+}
 
-    // 6. Bidirectional Characters
-    if is_prohibited_bidirectional_text(&normalized) {
-        return Err(Error(ErrorCause::ProhibitedBidirectionalText));
+/// A string which has been prepared with SASLprep.
+///
+/// Converting a `String` with `TryFrom` prepares it, reusing its buffer if it
+/// is already prepared.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PreppedString(String);
+
+impl PreppedString {
+    /// Wraps a string which is known to have already been prepared with
+    /// SASLprep, e.g. one loaded from a database of prepared values, without
+    /// checking it.
+    ///
+    /// Passing a string which has not been prepared will not cause undefined
+    /// behavior, but comparisons against properly prepared strings may fail.
+    #[must_use]
+    pub fn from_trusted(s: String) -> PreppedString {
+        PreppedString(s)
     }
 
-    // 7 Unassigned Code Points
-    let unassigned = normalized
-        .chars()
-        .find(|&c| tables::unassigned_code_point(c));
-    if let Some(c) = unassigned {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+    /// Returns the prepared string as a `&str`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 
-    Ok(Cow::Owned(normalized))
+    /// Returns the prepared string as a `String`.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0
+    }
 }
 
-/// Prepares a string with the Nodeprep profile of the stringprep algorithm.
-///
-/// Nameprep is defined in [RFC 3920, Appendix A][].
-///
-/// [RFC 3920, Appendix A]: https://tools.ietf.org/html/rfc3920#appendix-A
-pub fn nodeprep(s: &str) -> Result<Cow<'_, str>, Error> {
-    // fast path for common ascii text
-    if s.chars()
-        .all(|c| matches!(c, '['..='~' | '0'..='9' | '('..='.' | '#'..='%'))
-    {
-        return Ok(Cow::Borrowed(s));
+impl TryFrom<String> for PreppedString {
+    type Error = (Error, String);
+
+    fn try_from(s: String) -> Result<PreppedString, (Error, String)> {
+        let prepared = match saslprep_stored(&s) {
+            Ok(Cow::Borrowed(_)) => None,
+            Ok(Cow::Owned(prepared)) => Some(prepared),
+            Err(e) => return Err((e, s)),
+        };
+        match prepared {
+            Some(prepared) if prepared != s => Ok(PreppedString(prepared)),
+            _ => Ok(PreppedString(s)),
+        }
     }
+}
 
-    // A.3. Mapping
-    let mapped = s
-        .chars()
-        .filter(|&c| !tables::commonly_mapped_to_nothing(c))
-        .flat_map(tables::case_fold_for_nfkc);
+impl Deref for PreppedString {
+    type Target = str;
 
-    // A.4. Normalization
-    let normalized = mapped.nfkc().collect::<String>();
+    fn deref(&self) -> &str {
+        &self.0
+    }
+}
 
-    // A.5. Prohibited Output
-    let prohibited = normalized.chars().find(|&c| {
-        tables::ascii_space_character(c) /* C.1.1 */ ||
-            tables::non_ascii_space_character(c) /* C.1.2 */ ||
-            tables::ascii_control_character(c) /* C.2.1 */ ||
-            tables::non_ascii_control_character(c) /* C.2.2 */ ||
-            tables::private_use(c) /* C.3 */ ||
-            tables::non_character_code_point(c) /* C.4 */ ||
-            tables::surrogate_code(c) /* C.5 */ ||
-            tables::inappropriate_for_plain_text(c) /* C.6 */ ||
-            tables::inappropriate_for_canonical_representation(c) /* C.7 */ ||
-            tables::change_display_properties_or_deprecated(c) /* C.9 */ ||
-            tables::tagging_character(c) /* C.9 */ ||
-            prohibited_node_character(c)
-    });
-    if let Some(c) = prohibited {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+impl AsRef<str> for PreppedString {
+    fn as_ref(&self) -> &str {
+        &self.0
     }
+}
 
-    // A.6. Bidirectional Characters
-    if is_prohibited_bidirectional_text(&normalized) {
-        return Err(Error(ErrorCause::ProhibitedBidirectionalText));
+impl AsRef<[u8]> for PreppedString {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
     }
+}
 
-    let unassigned = normalized
-        .chars()
-        .find(|&c| tables::unassigned_code_point(c));
-    if let Some(c) = unassigned {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+#[cfg(feature = "std")]
+impl AsRef<Path> for PreppedString {
+    fn as_ref(&self) -> &Path {
+        Path::new(&self.0)
     }
+}
 
-    Ok(Cow::Owned(normalized))
+impl fmt::Display for PreppedString {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, fmt)
+    }
 }
 
-// Additional characters not allowed in JID nodes, by RFC3920.
-fn prohibited_node_character(c: char) -> bool {
-    matches!(c, '"' | '&' | '\'' | '/' | ':' | '<' | '>' | '@')
+impl From<PreppedString> for String {
+    fn from(s: PreppedString) -> String {
+        s.0
+    }
 }
 
-/// Prepares a string with the Resourceprep profile of the stringprep algorithm.
+/// A string which has been prepared with the profile `P`.
 ///
-/// Nameprep is defined in [RFC 3920, Appendix B][].
+/// Strings prepared with different profiles have different types, so they
+/// can't be mixed up or compared with each other. They are created with the
+/// `prepare` function of each profile, e.g. `SaslPrep::prepare`:
 ///
-/// [RFC 3920, Appendix B]: https://tools.ietf.org/html/rfc3920#appendix-B
-pub fn resourceprep(s: &str) -> Result<Cow<'_, str>, Error> {
-    // fast path for ascii text
-    if s.chars().all(|c| matches!(c, ' '..='~')) {
-        return Ok(Cow::Borrowed(s));
-    }
-
-    // B.3. Mapping
-    let mapped = s
-        .chars()
-        .filter(|&c| !tables::commonly_mapped_to_nothing(c))
-        .collect::<String>();
-
-    // B.4. Normalization
-    let normalized = mapped.nfkc().collect::<String>();
+/// ```
+/// use stringprep::{NamePrep, SaslPrep};
+///
+/// let password = SaslPrep::prepare("I\u{00AD}X").unwrap();
+/// assert_eq!(&*password, "IX");
+/// assert_eq!(password, SaslPrep::prepare("\u{2168}").unwrap());
+///
+/// let label = NamePrep::prepare("Example").unwrap();
+/// assert_eq!(label.as_str(), "example");
+/// ```
+///
+/// Strings prepared with different profiles can't be compared:
+///
+/// ```compile_fail
+/// use stringprep::{NamePrep, SaslPrep};
+///
+/// assert_eq!(SaslPrep::prepare("a").unwrap(), NamePrep::prepare("a").unwrap());
+/// ```
+pub struct PreparedString<P>(Cow<'static, str>, PhantomData<P>);
 
-    // B.5. Prohibited Output
-    let prohibited = normalized.chars().find(|&c| {
-        tables::non_ascii_space_character(c) /* C.1.2 */ ||
-            tables::ascii_control_character(c) /* C.2.1 */ ||
-            tables::non_ascii_control_character(c) /* C.2.2 */ ||
-            tables::private_use(c) /* C.3 */ ||
-            tables::non_character_code_point(c) /* C.4 */ ||
-            tables::surrogate_code(c) /* C.5 */ ||
-            tables::inappropriate_for_plain_text(c) /* C.6 */ ||
-            tables::inappropriate_for_canonical_representation(c) /* C.7 */ ||
-            tables::change_display_properties_or_deprecated(c) /* C.9 */ ||
-            tables::tagging_character(c) /* C.9 */
-    });
-    if let Some(c) = prohibited {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+impl<P> PreparedString<P> {
+    fn new(s: Cow<'_, str>) -> PreparedString<P> {
+        PreparedString(Cow::Owned(s.into_owned()), PhantomData)
     }
 
-    // B.6. Bidirectional Characters
-    if is_prohibited_bidirectional_text(&normalized) {
-        return Err(Error(ErrorCause::ProhibitedBidirectionalText));
+    /// Wraps a string which is known to have already been prepared with the
+    /// profile `P`, without checking it.
+    ///
+    /// Passing a string which has not been prepared will not cause undefined
+    /// behavior, but comparisons against properly prepared strings may fail.
+    #[must_use]
+    pub fn from_trusted(s: String) -> PreparedString<P> {
+        PreparedString(Cow::Owned(s), PhantomData)
     }
 
-    let unassigned = normalized
-        .chars()
-        .find(|&c| tables::unassigned_code_point(c));
-    if let Some(c) = unassigned {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+    /// Returns the prepared string as a `&str`.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.0
     }
 
-    Ok(Cow::Owned(normalized))
+    /// Returns the prepared string as a `String`.
+    #[must_use]
+    pub fn into_string(self) -> String {
+        self.0.into_owned()
+    }
 }
 
-/// Prepares a string according to the procedures described in Section 7 of
-/// [ITU-T Recommendation X.520 (2019)](https://www.itu.int/rec/T-REC-X.520-201910-I/en).
-///
-/// Note that this function does _not_ remove leading, trailing, or inner
-/// spaces as described in Section 7.6, because the characters needing removal
-/// will vary across the matching rules and ASN.1 syntaxes used.
-pub fn x520prep(s: &str, case_fold: bool) -> Result<Cow<'_, str>, Error> {
-    if s.is_empty() {
-        return Err(Error(ErrorCause::EmptyString));
-    }
-    if s.chars()
-        .all(|c| matches!(c, ' '..='~') && (!case_fold || c.is_ascii_lowercase()))
-    {
-        return Ok(Cow::Borrowed(s));
+// The trait impls are written out rather than derived, since deriving them
+// would require `P` to implement them as well.
+impl<P> fmt::Debug for PreparedString<P> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt.debug_tuple("PreparedString").field(&self.0).finish()
     }
+}
 
-    // 1. Transcode
-    // Already done because &str is enforced to be Unicode.
+impl<P> Clone for PreparedString<P> {
+    fn clone(&self) -> PreparedString<P> {
+        PreparedString(self.0.clone(), PhantomData)
+    }
+}
 
-    // 2. Map
-    let mapped = s
-        .chars()
-        .filter(|&c| !tables::x520_mapped_to_nothing(c))
-        .map(|c| {
-            if tables::x520_mapped_to_space(c) {
-                ' '
-            } else {
-                c
-            }
-        });
+impl<P> PartialEq for PreparedString<P> {
+    fn eq(&self, other: &PreparedString<P>) -> bool {
+        self.0 == other.0
+    }
+}
 
-    // 3. Normalize
-    let normalized = if case_fold {
-        mapped
-            .flat_map(tables::case_fold_for_nfkc)
-            .collect::<String>()
-    } else {
-        mapped.nfkc().collect::<String>()
-    };
+impl<P> Eq for PreparedString<P> {}
 
-    // 4. Prohibit
-    let prohibited = normalized.chars().find(
-        |&c| {
-            tables::unassigned_code_point(c)
-                || tables::private_use(c)
-                || tables::non_character_code_point(c)
-                || tables::surrogate_code(c)
-                || c == '\u{FFFD}'
-        }, // REPLACEMENT CHARACTER
-    );
-    if let Some(c) = prohibited {
-        return Err(Error(ErrorCause::ProhibitedCharacter(c)));
+impl<P> PartialOrd for PreparedString<P> {
+    fn partial_cmp(&self, other: &PreparedString<P>) -> Option<Ordering> {
+        Some(self.cmp(other))
     }
-    // From ITU-T Recommendation X.520, Section 7.4:
-    // "The first code point of a string is prohibited from being a combining character."
-    match s.chars().next() {
-        Some(c) => {
-            if c.general_category_group() == GeneralCategoryGroup::Mark {
-                return Err(Error(ErrorCause::StartsWithCombiningCharacter));
-            }
-        }
-        None => return Err(Error(ErrorCause::EmptyString)),
+}
+
+impl<P> Ord for PreparedString<P> {
+    fn cmp(&self, other: &PreparedString<P>) -> Ordering {
+        self.0.cmp(&other.0)
     }
+}
 
-    // 5. Check bidi
-    // From ITU-T Recommendation X.520, Section 7.4:
-    // "There are no bidirectional restrictions. The output string is the input string."
-    // So there is nothing to do for this step.
+impl<P> Hash for PreparedString<P> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.0.hash(state)
+    }
+}
 
-    // 6. Insignificant Character Removal
-    // Done in calling functions.
+impl<P> Deref for PreparedString<P> {
+    type Target = str;
 
-    Ok(normalized.into())
+    fn deref(&self) -> &str {
+        &self.0
+    }
 }
 
-#[cfg(test)]
-mod test {
-    use super::*;
+impl<P> AsRef<str> for PreparedString<P> {
+    fn as_ref(&self) -> &str {
+        &self.0
+    }
+}
 
-    fn assert_prohibited_character<T>(result: Result<T, Error>) {
-        match result {
-            Err(Error(ErrorCause::ProhibitedCharacter(_))) => (),
-            _ => panic!(),
-        }
+impl<P> AsRef<[u8]> for PreparedString<P> {
+    fn as_ref(&self) -> &[u8] {
+        self.0.as_bytes()
     }
+}
 
-    fn assert_starts_with_combining_char<T>(result: Result<T, Error>) {
-        match result {
-            Err(Error(ErrorCause::StartsWithCombiningCharacter)) => (),
-            _ => panic!(),
-        }
+#[cfg(feature = "std")]
+impl<P> AsRef<Path> for PreparedString<P> {
+    fn as_ref(&self) -> &Path {
+        Path::new(&*self.0)
     }
+}
 
-    // RFC4013, 3. Examples
-    #[test]
-    fn saslprep_examples() {
-        assert_prohibited_character(saslprep("\u{0007}"));
+impl<P> Borrow<str> for PreparedString<P> {
+    fn borrow(&self) -> &str {
+        &self.0
     }
+}
 
-    #[test]
-    fn nodeprep_examples() {
-        assert_prohibited_character(nodeprep(" "));
-        assert_prohibited_character(nodeprep("\u{00a0}"));
-        assert_prohibited_character(nodeprep("foo@bar"));
+impl<P> fmt::Display for PreparedString<P> {
+    fn fmt(&self, fmt: &mut fmt::Formatter) -> fmt::Result {
+        fmt::Display::fmt(&self.0, fmt)
     }
+}
 
-    #[test]
-    fn resourceprep_examples() {
-        assert_eq!("foo@bar", resourceprep("foo@bar").unwrap());
+impl<P> From<PreparedString<P>> for String {
+    fn from(s: PreparedString<P>) -> String {
+        s.into_string()
+    }
+}
+
+/// Prepares a password with SASLprep and appends channel binding data to its
+/// UTF-8 encoding.
+///
+/// This is a convenience for SCRAM `-PLUS` mechanisms, ensuring the password
+/// is prepared before, rather than after, the channel binding data is added.
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_concat_channel_binding(
+    password: &str,
+    channel_binding: &[u8],
+) -> Result<Vec<u8>, Error> {
+    let prepared = saslprep_stored(password)?;
+    let mut out = Vec::with_capacity(prepared.len() + channel_binding.len());
+    out.extend_from_slice(prepared.as_bytes());
+    out.extend_from_slice(channel_binding);
+    Ok(out)
+}
+
+/// Prepares a string with the SASLprep profile of the stringprep algorithm,
+/// writing the output into `buf` rather than allocating.
+///
+/// An error is returned if the prepared string does not fit in `buf`.
+#[must_use = "the prepared string must be used"]
+pub fn saslprep_short<'a, const N: usize>(s: &str, buf: &'a mut [u8; N]) -> Result<&'a str, Error> {
+    let mut len = 0;
+
+    // 2.1 Mapping and 2.2 Normalization
+    for c in s.chars().filter_map(tables::map_char_saslprep).nfkc() {
+        let end = len + c.len_utf8();
+        if end > N {
+            return Err(Error(ErrorKind::OutputTooLarge, None));
+        }
+        c.encode_utf8(&mut buf[len..end]);
+        len = end;
+    }
+
+    let normalized = str::from_utf8(&buf[..len]).unwrap();
+    check_inner(normalized, &SaslPrep)?;
+
+    Ok(normalized)
+}
+
+/// Prepares a string with SASLprep as it arrives in chunks.
+///
+/// The mapping step is applied to each chunk as it is fed in, and the rest of
+/// the algorithm when `finish` is called, so the caller does not need to
+/// concatenate the chunks itself.
+///
+/// ```
+/// use stringprep::SaslPrepChunked;
+///
+/// let mut chunked = SaslPrepChunked::new();
+/// chunked.feed("I\u{00AD}").unwrap();
+/// chunked.feed("X").unwrap();
+/// assert_eq!(chunked.finish().unwrap(), "IX");
+/// ```
+#[derive(Debug, Default)]
+pub struct SaslPrepChunked {
+    mapped: String,
+}
+
+impl SaslPrepChunked {
+    /// Creates a new chunked SASLprep processor.
+    #[must_use]
+    pub fn new() -> SaslPrepChunked {
+        SaslPrepChunked::default()
+    }
+
+    /// Feeds the next chunk of the string to the processor.
+    ///
+    /// An error is returned early if the chunk contains an ASCII control
+    /// character, since normalization can't remove one and the string would be
+    /// rejected by `finish` anyway.
+    #[must_use = "the check result must be used"]
+    pub fn feed(&mut self, chunk: &str) -> Result<(), Error> {
+        // 2.1 Mapping
+        for c in chunk.chars().filter_map(tables::map_char_saslprep) {
+            if tables::ascii_control_character(c) {
+                return Err(Error::prohibited(c, Some("C.2.1")));
+            }
+            self.mapped.push(c);
+        }
+        Ok(())
+    }
+
+    /// Finishes preparing the string.
+    #[must_use = "the prepared string must be used"]
+    pub fn finish(self) -> Result<String, Error> {
+        // 2.2 Normalization
+        let normalized = if tables::is_all_saslprep_ascii(&self.mapped) {
+            self.mapped
+        } else {
+            self.mapped.nfkc().collect::<String>()
+        };
+
+        check_inner(&normalized, &SaslPrep)?;
+
+        Ok(normalized)
+    }
+}
+
+/// Describes what SASLprep does to each character of a string.
+///
+/// The output is intended for debugging, e.g. of authentication failures, and
+/// its format is not stable.
+///
+/// Requires the `debug` Cargo feature.
+#[cfg(feature = "debug")]
+#[must_use]
+pub fn saslprep_debug(s: &str) -> String {
+    use alloc::string::ToString;
+    use core::fmt::Write;
+
+    let mut out = String::new();
+
+    // 2.1 Mapping
+    for c in s.chars() {
+        let fate = match tables::map_char_saslprep(c) {
+            Some(' ') if c != ' ' => format!("mapped to {} (C.1.2)", describe_char(' ')),
+            Some(_) => "unchanged".to_string(),
+            None => "mapped to nothing (B.1)".to_string(),
+        };
+        writeln!(out, "{} \u{2192} {}", describe_char(c), fate).unwrap();
+    }
+
+    // 2.2 Normalization
+    let mapped = s
+        .chars()
+        .filter_map(tables::map_char_saslprep)
+        .collect::<String>();
+    let normalized = mapped.nfkc().collect::<String>();
+    if normalized != mapped {
+        writeln!(out, "NFKC normalization produced {:?}", normalized).unwrap();
+    }
+
+    // 2.3 Prohibited Output
+    for c in normalized.chars() {
+        if let Some(table) = saslprep_prohibited_table(c) {
+            writeln!(out, "{} \u{2192} prohibited ({})", describe_char(c), table).unwrap();
+        }
+    }
+
+    // 2.4. Bidirectional Characters
+    if is_prohibited_bidirectional_text(&normalized) {
+        writeln!(out, "prohibited bidirectional text").unwrap();
+    }
+
+    // 2.5 Unassigned Code Points
+    for c in normalized.chars() {
+        if tables::unassigned_code_point(c) {
+            writeln!(
+                out,
+                "{} \u{2192} prohibited (A.1 unassigned code point)",
+                describe_char(c)
+            )
+            .unwrap();
+        }
+    }
+
+    out
+}
+
+#[cfg(feature = "debug")]
+fn describe_char(c: char) -> String {
+    format!("U+{:04X} {:?}", c as u32, c)
+}
+
+#[cfg(feature = "debug")]
+fn saslprep_prohibited_table(c: char) -> Option<&'static str> {
+    let description = match tables::prohibited_by(c)? {
+        "C.1.2" => "C.1.2 non-ASCII space character",
+        "C.2.1" => "C.2.1 ASCII control character",
+        "C.2.2" => "C.2.2 non-ASCII control character",
+        "C.3" => "C.3 private use",
+        "C.4" => "C.4 non-character code point",
+        "C.5" => "C.5 surrogate code",
+        "C.6" => "C.6 inappropriate for plain text",
+        "C.7" => "C.7 inappropriate for canonical representation",
+        "C.8" => "C.8 change display properties or deprecated",
+        "C.9" => "C.9 tagging character",
+        // unassigned code points are reported separately
+        _ => return None,
+    };
+    Some(description)
+}
+
+/// The Unicode normalization form applied by a `Profile`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Normalization {
+    /// Normalization Form C.
+    Nfc,
+    /// Normalization Form KC, as required by RFC 3454.
+    Nfkc,
+}
+
+/// A stringprep profile, defining the steps run by `prepare`.
+///
+/// Profiles are typically composed from the predicates in the `tables`
+/// module:
+///
+/// ```
+/// use stringprep::{prepare, tables, Normalization, Profile};
+///
+/// struct NoSpaces;
+///
+/// impl Profile for NoSpaces {
+///     fn map_char(&self, c: char) -> Option<char> {
+///         tables::map_char_saslprep(c)
+///     }
+///
+///     fn is_prohibited(&self, c: char) -> bool {
+///         tables::ascii_space_character(c) || tables::non_ascii_space_character(c)
+///     }
+///
+///     fn normalization(&self) -> Normalization {
+///         Normalization::Nfkc
+///     }
+///
+///     fn check_bidi(&self) -> bool {
+///         true
+///     }
+/// }
+///
+/// assert_eq!(prepare("I\u{00AD}X", &NoSpaces).unwrap(), "IX");
+/// assert!(prepare("a b", &NoSpaces).is_err());
+/// ```
+pub trait Profile {
+    /// Maps a single character, returning `None` if it is mapped to nothing.
+    fn map_char(&self, c: char) -> Option<char>;
+
+    /// Maps a string.
+    ///
+    /// The default implementation maps each character with `map_char`.
+    /// Profiles which map characters to several characters, such as those
+    /// case folding with table B.2, must override it.
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        if s.chars().all(|c| self.map_char(c) == Some(c)) {
+            Cow::Borrowed(s)
+        } else {
+            Cow::Owned(s.chars().filter_map(|c| self.map_char(c)).collect())
+        }
+    }
+
+    /// Determines if a character is prohibited in the output of the profile.
+    fn is_prohibited(&self, c: char) -> bool;
+
+    /// Returns the identifier of the RFC 3454 table prohibiting `c` in the
+    /// profile, e.g. `"C.2.1"`, which is included in error messages.
+    ///
+    /// Defaults to `None`.
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        let _ = c;
+        None
+    }
+
+    /// Returns the normalization form applied after mapping.
+    fn normalization(&self) -> Normalization;
+
+    /// Determines if the bidirectional text check of RFC 3454 section 6 is
+    /// run.
+    fn check_bidi(&self) -> bool;
+
+    /// Determines if unassigned code points are rejected.
+    ///
+    /// Defaults to `true`, as required for stored strings.
+    fn check_unassigned(&self) -> bool {
+        true
+    }
+}
+
+/// Prepares a string with a stringprep profile.
+///
+/// The string is mapped, normalized and checked for prohibited characters,
+/// prohibited bidirectional text and unassigned code points as described in
+/// [RFC 3454][], with each step defined by `profile`.
+///
+/// [RFC 3454]: https://tools.ietf.org/html/rfc3454
+#[must_use = "the prepared string must be used"]
+pub fn prepare<'a, P: Profile + ?Sized>(s: &'a str, profile: &P) -> Result<Cow<'a, str>, Error> {
+    // 3. Mapping
+    let mapped = profile.map(s);
+
+    // 4. Normalization
+    let normalized = normalize(&mapped, profile.normalization());
+    trace!(
+        "stringprep: mapping and normalization produced length={}",
+        normalized.len()
+    );
+
+    check_inner(&normalized, profile)?;
+
+    Ok(Cow::Owned(normalized))
+}
+
+// RFC3454, 4.
+fn normalize(s: &str, normalization: Normalization) -> String {
+    match normalization {
+        Normalization::Nfc => s.nfc().collect(),
+        Normalization::Nfkc => s.nfkc().collect(),
+    }
+}
+
+// RFC3454, 5. - 7.
+fn check_inner<P: Profile + ?Sized>(s: &str, profile: &P) -> Result<(), Error> {
+    // 5. Prohibited Output
+    if let Some(c) = s.chars().find(|&c| profile.is_prohibited(c)) {
+        trace!(
+            "stringprep: returning prohibited character error for U+{:04X}",
+            c as u32
+        );
+        return Err(Error::prohibited(c, profile.prohibited_by(c)));
+    }
+
+    // 6. Bidirectional Characters
+    if profile.check_bidi() && is_prohibited_bidirectional_text(s) {
+        trace!("stringprep: returning prohibited bidirectional text error");
+        return Err(Error(ErrorKind::ProhibitedBidirectionalText, None));
+    }
+
+    // 7. Unassigned Code Points
+    if profile.check_unassigned() {
+        // the trace call is a no-op without the log feature
+        #[allow(clippy::question_mark)]
+        if let Err(e) = tables::check_no_unassigned(s) {
+            trace!("stringprep: returning unassigned code point error");
+            return Err(e);
+        }
+    }
+
+    Ok(())
+}
+
+/// The SASLprep profile, as implemented by `saslprep_stored`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct SaslPrep;
+
+impl SaslPrep {
+    /// Prepares a string with SASLprep, as `saslprep_stored` does.
+    #[must_use = "the prepared string must be used"]
+    pub fn prepare(s: &str) -> Result<PreparedString<SaslPrep>, Error> {
+        saslprep_stored(s).map(PreparedString::new)
+    }
+}
+
+impl Profile for SaslPrep {
+    fn map_char(&self, c: char) -> Option<char> {
+        tables::map_char_saslprep(c)
+    }
+
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        map_saslprep(s)
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        prohibited_saslprep(c)
+    }
+
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        tables::prohibited_by(c)
+    }
+
+    fn normalization(&self) -> Normalization {
+        Normalization::Nfkc
+    }
+
+    fn check_bidi(&self) -> bool {
+        true
+    }
+}
+
+/// The Nameprep profile, as implemented by `nameprep`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NamePrep;
+
+impl NamePrep {
+    /// Prepares a string with Nameprep.
+    #[must_use = "the prepared string must be used"]
+    pub fn prepare(s: &str) -> Result<PreparedString<NamePrep>, Error> {
+        nameprep(s).map(PreparedString::new)
+    }
+}
+
+impl Profile for NamePrep {
+    /// Maps a character with tables B.1 and B.2.
+    ///
+    /// Characters which case fold to several characters are left unchanged;
+    /// `map` applies the full mapping.
+    fn map_char(&self, c: char) -> Option<char> {
+        map_char_case_folding(c)
+    }
+
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        map_nameprep(s)
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        prohibited_nameprep(c)
+    }
+
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        tables::prohibited_by(c)
+    }
+
+    fn normalization(&self) -> Normalization {
+        Normalization::Nfkc
+    }
+
+    fn check_bidi(&self) -> bool {
+        true
+    }
+}
+
+/// The Nodeprep profile, as implemented by `nodeprep`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct NodePrep;
+
+impl NodePrep {
+    /// Prepares a string with Nodeprep.
+    #[must_use = "the prepared string must be used"]
+    pub fn prepare(s: &str) -> Result<PreparedString<NodePrep>, Error> {
+        nodeprep(s).map(PreparedString::new)
+    }
+}
+
+impl Profile for NodePrep {
+    /// Maps a character with tables B.1 and B.2.
+    ///
+    /// Characters which case fold to several characters are left unchanged;
+    /// `map` applies the full mapping.
+    fn map_char(&self, c: char) -> Option<char> {
+        map_char_case_folding(c)
+    }
+
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        map_nameprep(s)
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        prohibited_nodeprep(c)
+    }
+
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        if tables::ascii_space_character(c) {
+            Some("C.1.1")
+        } else if tables::nodeprep_prohibited_ascii(c) {
+            None
+        } else {
+            tables::prohibited_by(c)
+        }
+    }
+
+    fn normalization(&self) -> Normalization {
+        Normalization::Nfkc
+    }
+
+    fn check_bidi(&self) -> bool {
+        true
+    }
+}
+
+/// The Resourceprep profile, as implemented by `resourceprep`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash)]
+pub struct ResourcePrep;
+
+impl ResourcePrep {
+    /// Prepares a string with Resourceprep.
+    #[must_use = "the prepared string must be used"]
+    pub fn prepare(s: &str) -> Result<PreparedString<ResourcePrep>, Error> {
+        resourceprep(s).map(PreparedString::new)
+    }
+}
+
+impl Profile for ResourcePrep {
+    fn map_char(&self, c: char) -> Option<char> {
+        if tables::commonly_mapped_to_nothing(c) {
+            None
+        } else {
+            Some(c)
+        }
+    }
+
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        tables::remove_b1_chars(s)
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        prohibited_saslprep(c)
+    }
+
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        tables::prohibited_by(c)
+    }
+
+    fn normalization(&self) -> Normalization {
+        Normalization::Nfkc
+    }
+
+    fn check_bidi(&self) -> bool {
+        true
+    }
+}
+
+// RFC3454, B.1. and the single character mappings of B.2.
+fn map_char_case_folding(c: char) -> Option<char> {
+    if tables::commonly_mapped_to_nothing(c) {
+        return None;
+    }
+
+    let mut folded = tables::case_fold_for_nfkc(c);
+    match (folded.next(), folded.next()) {
+        (Some(m), None) => Some(m),
+        _ => Some(c),
+    }
+}
+
+// A profile with some of its checks disabled, as built by `PrepOptions`.
+struct WithChecks {
+    profile: &'static dyn Profile,
+    check_bidi: bool,
+    check_unassigned: bool,
+}
+
+impl Profile for WithChecks {
+    fn map_char(&self, c: char) -> Option<char> {
+        self.profile.map_char(c)
+    }
+
+    fn map<'a>(&self, s: &'a str) -> Cow<'a, str> {
+        self.profile.map(s)
+    }
+
+    fn is_prohibited(&self, c: char) -> bool {
+        self.profile.is_prohibited(c)
+    }
+
+    fn prohibited_by(&self, c: char) -> Option<&'static str> {
+        self.profile.prohibited_by(c)
+    }
+
+    fn normalization(&self) -> Normalization {
+        self.profile.normalization()
+    }
+
+    fn check_bidi(&self) -> bool {
+        self.check_bidi
+    }
+
+    fn check_unassigned(&self) -> bool {
+        self.check_unassigned
+    }
+}
+
+// RFC4013, 2.1, skipped if there is nothing to map
+fn map_saslprep(s: &str) -> Cow<'_, str> {
+    // only non-ASCII characters are mapped, so the ASCII prefix can be skipped
+    let non_ascii = &s[tables::first_non_ascii_position(s).unwrap_or(s.len())..];
+    if tables::count_non_ascii_space_chars(non_ascii) == 0
+        && !tables::has_commonly_mapped_to_nothing_char(non_ascii)
+    {
+        trace!("saslprep: mapping step skipped");
+        return Cow::Borrowed(s);
+    }
+
+    Cow::Owned(s.chars().filter_map(tables::map_char_saslprep).collect())
+}
+
+// RFC3491, 3. and RFC3920, A.3., skipping case folding if there is nothing to
+// fold
+fn map_nameprep(s: &str) -> Cow<'_, str> {
+    if tables::has_ascii_uppercase(s) || tables::has_case_foldable_char(s) {
+        Cow::Owned(s.chars().flat_map(tables::map_chars_nameprep).collect())
+    } else {
+        tables::remove_b1_chars(s)
+    }
+}
+
+// RFC4013, 2.3 and RFC3920, B.5.
+fn prohibited_saslprep(c: char) -> bool {
+    tables::ascii_control_character(c) /* C.2.1 */ || prohibited_nameprep(c)
+}
+
+// RFC3491, 5.
+fn prohibited_nameprep(c: char) -> bool {
+    tables::non_ascii_space_character(c) /* C.1.2 */ ||
+        tables::non_ascii_control_character(c) /* C.2.2 */ ||
+        tables::private_use(c) /* C.3 */ ||
+        tables::non_character_code_point(c) /* C.4 */ ||
+        tables::surrogate_code(c) /* C.5 */ ||
+        tables::inappropriate_for_plain_text(c) /* C.6 */ ||
+        tables::inappropriate_for_canonical_representation(c) /* C.7 */ ||
+        tables::change_display_properties_or_deprecated(c) /* C.8 */ ||
+        tables::tagging_character(c) /* C.9 */
+}
+
+// RFC3920, A.5.
+fn prohibited_nodeprep(c: char) -> bool {
+    tables::ascii_space_character(c) /* C.1.1 */ ||
+        prohibited_saslprep(c) ||
+        tables::nodeprep_prohibited_ascii(c)
+}
+
+// RFC3454, 3. - 7., collecting every error rather than returning the first
+fn validate_inner<P: Profile + ?Sized>(s: &str, profile: &P) -> Vec<Error> {
+    let normalized = normalize(&profile.map(s), profile.normalization());
+
+    let mut errors = normalized
+        .chars()
+        .filter(|&c| profile.is_prohibited(c))
+        .map(|c| Error::prohibited(c, profile.prohibited_by(c)))
+        .collect::<Vec<_>>();
+
+    if profile.check_bidi() && is_prohibited_bidirectional_text(&normalized) {
+        errors.push(Error(ErrorKind::ProhibitedBidirectionalText, None));
+    }
+
+    if profile.check_unassigned() {
+        errors.extend(
+            normalized
+                .chars()
+                .filter(|&c| tables::unassigned_code_point(c))
+                .map(|c| Error(ErrorKind::UnassignedCodePoint(c), None)),
+        );
+    }
+
+    errors
+}
+
+// Compares two prepared strings as they are produced, checking each with
+// `StreamCheck`.
+fn eq_chars<I, P>(a: I, b: I, profile: &P) -> Result<bool, Error>
+where
+    I: Iterator<Item = char>,
+    P: Profile + ?Sized,
+{
+    let mut a = a.fuse();
+    let mut b = b.fuse();
+    let mut a_check = StreamCheck::default();
+    let mut b_check = StreamCheck::default();
+    let mut eq = true;
+
+    loop {
+        let (a_next, b_next) = (a.next(), b.next());
+        if a_next.is_none() && b_next.is_none() {
+            break;
+        }
+        eq &= a_next == b_next;
+        if let Some(c) = a_next {
+            a_check.push(c, profile)?;
+        }
+        if let Some(c) = b_next {
+            b_check.push(c, profile)?;
+        }
+    }
+
+    a_check.finish(profile)?;
+    b_check.finish(profile)?;
+    Ok(eq)
+}
+
+// RFC3454, 5. - 7., checking the prepared string one character at a time.
+// Errors are reported in the same order as `check_inner`.
+#[derive(Default)]
+struct StreamCheck {
+    first_r_or_al: Option<bool>,
+    last_r_or_al: bool,
+    has_r_or_al: bool,
+    has_l: bool,
+    unassigned: Option<char>,
+}
+
+impl StreamCheck {
+    fn push<P: Profile + ?Sized>(&mut self, c: char, profile: &P) -> Result<(), Error> {
+        // 5. Prohibited Output
+        if profile.is_prohibited(c) {
+            return Err(Error::prohibited(c, profile.prohibited_by(c)));
+        }
+
+        // 6. Bidirectional Characters
+        // a single lookup of the bidirectional class covers both D.1 and D.2
+        let class = bidi_class(c);
+        let r_or_al = matches!(class, BidiClass::R | BidiClass::AL);
+        self.first_r_or_al.get_or_insert(r_or_al);
+        self.last_r_or_al = r_or_al;
+        self.has_r_or_al |= r_or_al;
+        self.has_l |= class == BidiClass::L;
+
+        // 7. Unassigned Code Points
+        // table A.1 contains no ASCII characters
+        if self.unassigned.is_none() && !c.is_ascii() && tables::unassigned_code_point(c) {
+            self.unassigned = Some(c);
+        }
+
+        Ok(())
+    }
+
+    fn finish<P: Profile + ?Sized>(self, profile: &P) -> Result<(), Error> {
+        if profile.check_bidi()
+            && self.has_r_or_al
+            && (self.has_l || self.first_r_or_al != Some(true) || !self.last_r_or_al)
+        {
+            return Err(Error(ErrorKind::ProhibitedBidirectionalText, None));
+        }
+
+        match self.unassigned {
+            Some(c) if profile.check_unassigned() => {
+                Err(Error(ErrorKind::UnassignedCodePoint(c), None))
+            }
+            _ => Ok(()),
+        }
+    }
+}
+
+// RFC3454, 6. Bidirectional Characters
+fn is_prohibited_bidirectional_text(s: &str) -> bool {
+    if s.contains(tables::bidi_r_or_al) {
+        // 2) If a string contains any RandALCat character, the string
+        // MUST NOT contain any LCat character.
+        if s.contains(tables::bidi_l) {
+            return true;
+        }
+
+        // 3) If a string contains any RandALCat character, a RandALCat
+        // character MUST be the first character of the string, and a
+        // RandALCat character MUST be the last character of the string.
+        if !tables::bidi_r_or_al(s.chars().next().unwrap())
+            || !tables::bidi_r_or_al(s.chars().next_back().unwrap())
+        {
+            return true;
+        }
+    }
+
+    false
+}
+
+/// Prepares a domain name label with the Nameprep profile of the stringprep
+/// algorithm.
+///
+/// Nameprep is defined in [RFC 3491][]. The bidirectional text check applies
+/// to a single label, so use `nameprep_domain` to prepare a full domain name.
+///
+/// [RFC 3491]: https://tools.ietf.org/html/rfc3491
+#[must_use = "the prepared string must be used"]
+pub fn nameprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    // fast path for ascii text
+    if is_nameprep_ascii(s) {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    // letters, digits and hyphens are only case folded
+    if tables::is_all_ascii_label_chars(s) {
+        return Ok(Cow::Owned(s.to_ascii_lowercase()));
+    }
+
+    prepare(s, &NamePrep)
+}
+
+/// Prepares each label of a domain name with Nameprep.
+///
+/// The name is split into labels on the separators listed in
+/// [RFC 3490, Section 3.1][], each label is prepared with `nameprep`, and the
+/// labels are joined with `.`. A trailing separator, as in a fully qualified
+/// domain name, is preserved, but an error is returned for any other empty
+/// label, or for a label which contains a separator after Nameprep.
+///
+/// [RFC 3490, Section 3.1]: https://tools.ietf.org/html/rfc3490#section-3.1
+#[must_use = "the prepared string must be used"]
+pub fn nameprep_domain(s: &str) -> Result<Cow<'_, str>, Error> {
+    let mut labels = s.split(tables::is_idna_label_separator).collect::<Vec<_>>();
+    let fully_qualified = labels.len() > 1 && labels.last() == Some(&"");
+    if fully_qualified {
+        labels.pop();
+    }
+
+    let mut prepared = Vec::with_capacity(labels.len());
+    for label in labels {
+        if label.is_empty() {
+            return Err(Error(ErrorKind::EmptyString, None));
+        }
+        let label = nameprep(label)?;
+        // normalization can produce a separator, e.g. from U+2488 DIGIT ONE
+        // FULL STOP, which would change the labels of the name
+        if let Some(c) = label.chars().find(|&c| tables::is_idna_label_separator(c)) {
+            return Err(Error::prohibited(c, None));
+        }
+        prepared.push(label);
+    }
+
+    let unchanged = !s.contains(|c| c != '.' && tables::is_idna_label_separator(c))
+        && prepared
+            .iter()
+            .all(|label| matches!(label, Cow::Borrowed(_)));
+    if unchanged {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    let mut out = prepared.join(".");
+    if fully_qualified {
+        out.push('.');
+    }
+    Ok(Cow::Owned(out))
+}
+
+// Determines if `s` is ASCII text which is unchanged by Nameprep.
+fn is_nameprep_ascii(s: &str) -> bool {
+    s.chars()
+        .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '.' || c == '-')
+}
+
+/// Determines if two strings are equal after being prepared with Nameprep.
+///
+/// An error is returned if either string is not valid for `nameprep`. As with
+/// `saslprep_eq`, the prepared strings are compared without being collected
+/// into new strings.
+#[must_use = "the comparison result must be used"]
+pub fn nameprep_eq(a: &str, b: &str) -> Result<bool, Error> {
+    // fast path for ascii text
+    if is_nameprep_ascii(a) && is_nameprep_ascii(b) {
+        return Ok(a == b);
+    }
+
+    eq_chars(nameprep_chars(a), nameprep_chars(b), &NamePrep)
+}
+
+/// Prepares a string with Nameprep, returning every problem found with it.
+///
+/// As with `saslprep_validate`, an empty `Vec` means that the string is valid.
+#[must_use]
+pub fn nameprep_validate(s: &str) -> Vec<Error> {
+    validate_inner(s, &NamePrep)
+}
+
+// RFC3491, 3. and 4., without collecting the result
+fn nameprep_chars(s: &str) -> impl Iterator<Item = char> + '_ {
+    s.chars().flat_map(tables::map_chars_nameprep).nfkc()
+}
+
+/// Prepares a domain name label with Nameprep and converts it to its ASCII
+/// Compatible Encoding.
+///
+/// Labels which are ASCII after Nameprep are returned unchanged, and all
+/// others are encoded with Punycode and prefixed with `xn--`, as described in
+/// the ToASCII operation of [RFC 3490][]. An error is returned if the result
+/// is empty or longer than the 63 byte limit on DNS labels, or if a label
+/// which is not ASCII already starts with `xn--`.
+///
+/// [RFC 3490]: https://tools.ietf.org/html/rfc3490
+#[must_use = "the prepared string must be used"]
+pub fn nameprep_to_ascii(label: &str) -> Result<String, Error> {
+    let prepared = nameprep(label)?;
+    let ace = if prepared.is_ascii() {
+        prepared.into_owned()
+    } else {
+        // RFC3490, 4.1., step 5.
+        if has_ace_prefix(&prepared) {
+            return Err(Error(ErrorKind::InvalidAce, None));
+        }
+        // RFC3490, 4.1., step 8. Every character takes at least one byte of
+        // the encoding, so long labels are rejected before running the
+        // quadratic Punycode encoder.
+        let min_len = ACE_PREFIX.len() + prepared.chars().count();
+        if min_len > tables::MAX_LABEL_BYTES {
+            return Err(Error(ErrorKind::TooLong(min_len), None));
+        }
+        match punycode::encode(&prepared) {
+            Some(encoded) => format!("{}{}", ACE_PREFIX, encoded),
+            None => return Err(Error(ErrorKind::InvalidPunycode, None)),
+        }
+    };
+
+    // RFC3490, 4.1., step 8.
+    tables::check_non_empty(&ace)?;
+    if !tables::check_label_max_bytes(&ace, tables::MAX_LABEL_BYTES) {
+        return Err(Error(ErrorKind::TooLong(ace.len()), None));
+    }
+    Ok(ace)
+}
+
+/// Decodes a domain name label from its ASCII Compatible Encoding and prepares
+/// it with Nameprep.
+///
+/// Labels without the `xn--` prefix are prepared as-is. As in the ToUnicode
+/// operation of [RFC 3490][], the decoded label is converted back with
+/// `nameprep_to_ascii`, and an error is returned unless the result matches
+/// `ace`, ignoring ASCII case. This rejects empty and non-canonical encodings.
+///
+/// [RFC 3490]: https://tools.ietf.org/html/rfc3490
+#[must_use = "the prepared string must be used"]
+pub fn nameprep_from_ace(ace: &str) -> Result<String, Error> {
+    if !has_ace_prefix(ace) {
+        return nameprep(ace).map(Cow::into_owned);
+    }
+
+    let decoded = match punycode::decode(&ace[ACE_PREFIX.len()..]) {
+        Some(decoded) => decoded,
+        None => return Err(Error(ErrorKind::InvalidPunycode, None)),
+    };
+
+    // RFC3490, 4.2., steps 6. and 7.
+    match nameprep_to_ascii(&decoded) {
+        Ok(ref reencoded) if reencoded.eq_ignore_ascii_case(ace) => {}
+        _ => return Err(Error(ErrorKind::InvalidAce, None)),
+    }
+
+    nameprep(&decoded).map(Cow::into_owned)
+}
+
+fn has_ace_prefix(label: &str) -> bool {
+    match label.get(..ACE_PREFIX.len()) {
+        Some(prefix) => prefix.eq_ignore_ascii_case(ACE_PREFIX),
+        None => false,
+    }
+}
+
+const ACE_PREFIX: &str = "xn--";
+
+/// Prepares a string with the Nodeprep profile of the stringprep algorithm.
+///
+/// Nodeprep is used for the localpart of an XMPP address, and is defined in
+/// [RFC 3920, Appendix A][] and [RFC 6122, Appendix A][].
+///
+/// [RFC 3920, Appendix A]: https://tools.ietf.org/html/rfc3920#appendix-A
+/// [RFC 6122, Appendix A]: https://tools.ietf.org/html/rfc6122#appendix-A
+#[must_use = "the prepared string must be used"]
+pub fn nodeprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    // fast path for common ascii text
+    if s.chars()
+        .all(|c| matches!(c, '['..='~' | '0'..='9' | '('..='.' | '#'..='%'))
+    {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    prepare(s, &NodePrep)
+}
+
+/// Prepares a string with the Resourceprep profile of the stringprep algorithm.
+///
+/// Resourceprep is used for the resourcepart of an XMPP address, and is
+/// defined in [RFC 3920, Appendix B][] and [RFC 6122, Appendix B][]. Unlike
+/// Nodeprep, it does not case fold, and allows spaces.
+///
+/// [RFC 3920, Appendix B]: https://tools.ietf.org/html/rfc3920#appendix-B
+/// [RFC 6122, Appendix B]: https://tools.ietf.org/html/rfc6122#appendix-B
+#[must_use = "the prepared string must be used"]
+pub fn resourceprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    // fast path for ascii text
+    if s.chars().all(|c| matches!(c, ' '..='~')) {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    prepare(s, &ResourcePrep)
+}
+
+/// Prepares a string according to the procedures described in Section 7 of
+/// [ITU-T Recommendation X.520 (2019)](https://www.itu.int/rec/T-REC-X.520-201910-I/en).
+///
+/// Note that this function does _not_ remove leading, trailing, or inner
+/// spaces as described in Section 7.6, because the characters needing removal
+/// will vary across the matching rules and ASN.1 syntaxes used.
+#[must_use = "the prepared string must be used"]
+pub fn x520prep(s: &str, case_fold: bool) -> Result<Cow<'_, str>, Error> {
+    tables::check_non_empty(s)?;
+    if s.chars()
+        .all(|c| matches!(c, ' '..='~') && (!case_fold || c.is_ascii_lowercase()))
+    {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    // 1. Transcode
+    // Already done because &str is enforced to be Unicode.
+
+    // 2. Map
+    let mapped = s
+        .chars()
+        .filter(|&c| !tables::x520_mapped_to_nothing(c))
+        .map(|c| {
+            if tables::x520_mapped_to_space(c) {
+                ' '
+            } else {
+                c
+            }
+        });
+
+    // 3. Normalize
+    let normalized = if case_fold {
+        mapped
+            .flat_map(tables::case_fold_for_nfkc)
+            .collect::<String>()
+    } else {
+        mapped.nfkc().collect::<String>()
+    };
+
+    // 4. Prohibit
+    let prohibited = normalized.chars().find(
+        |&c| {
+            tables::unassigned_code_point(c)
+                || tables::private_use(c)
+                || tables::non_character_code_point(c)
+                || tables::surrogate_code(c)
+                || c == '\u{FFFD}'
+        }, // REPLACEMENT CHARACTER
+    );
+    if let Some(c) = prohibited {
+        // U+FFFD is in table C.6, but X.520 prohibits it separately
+        let table = if c == '\u{FFFD}' {
+            None
+        } else {
+            tables::prohibited_by(c)
+        };
+        return Err(Error::prohibited(c, table));
+    }
+    // From ITU-T Recommendation X.520, Section 7.4:
+    // "The first code point of a string is prohibited from being a combining character."
+    match s.chars().next() {
+        Some(c) => {
+            if tables::is_combining_character(c) {
+                return Err(Error(ErrorKind::StartsWithCombiningCharacter, None));
+            }
+        }
+        None => return Err(Error(ErrorKind::EmptyString, None)),
+    }
+
+    // 5. Check bidi
+    // From ITU-T Recommendation X.520, Section 7.4:
+    // "There are no bidirectional restrictions. The output string is the input string."
+    // So there is nothing to do for this step.
+
+    // 6. Insignificant Character Removal
+    // Done in calling functions.
+
+    Ok(normalized.into())
+}
+
+/// Prepares an LDAP attribute value for case-ignore matching, as described in
+/// [RFC 4518][].
+///
+/// This applies `x520prep` with case folding, followed by the insignificant
+/// space handling of Section 2.6.1: the result starts and ends with exactly
+/// one space, and inner runs of spaces are replaced with exactly two spaces.
+/// A value consisting only of spaces, including the empty value, is prepared
+/// as two spaces.
+///
+/// [RFC 4518]: https://tools.ietf.org/html/rfc4518
+#[must_use = "the prepared string must be used"]
+pub fn ldapprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    ldapprep_inner(s, true)
+}
+
+/// Prepares an LDAP attribute value for case-exact matching, as described in
+/// [RFC 4518][].
+///
+/// This is the same as `ldapprep`, but without case folding.
+///
+/// [RFC 4518]: https://tools.ietf.org/html/rfc4518
+#[must_use = "the prepared string must be used"]
+pub fn ldapprep_case_exact(s: &str) -> Result<Cow<'_, str>, Error> {
+    ldapprep_inner(s, false)
+}
+
+fn ldapprep_inner(s: &str, case_fold: bool) -> Result<Cow<'_, str>, Error> {
+    if s.is_empty() {
+        return Ok(Cow::Borrowed("  "));
+    }
+    let prepared = ldapprep_steps(s, case_fold)?;
+
+    // RFC4518, 2.6.1. Insignificant Space Handling
+    let mut out = String::with_capacity(prepared.len() + 2);
+    for word in prepared.split(' ').filter(|w| !w.is_empty()) {
+        out.push_str(if out.is_empty() { " " } else { "  " });
+        out.push_str(word);
+    }
+    out.push_str(if out.is_empty() { "  " } else { " " });
+
+    Ok(Cow::Owned(out))
+}
+
+// RFC4518, 2.1. - 2.5.
+fn ldapprep_steps(s: &str, case_fold: bool) -> Result<Cow<'_, str>, Error> {
+    let mut prepared = x520prep(s, case_fold)?;
+
+    // 2.3. Normalize. x520prep only applies NFKC when it doesn't case fold,
+    // but table B.2 is designed to be followed by NFKC.
+    if case_fold && !unicode_normalization::is_nfkc(&prepared) {
+        prepared = Cow::Owned(prepared.nfkc().collect());
+    }
+
+    // 2.4. Prohibit, which includes table C.8 in addition to the characters
+    // prohibited by x520prep
+    if let Some(c) = prepared
+        .chars()
+        .find(|&c| tables::change_display_properties_or_deprecated(c))
+    {
+        return Err(Error::prohibited(c, Some("C.8")));
+    }
+
+    Ok(prepared)
+}
+
+/// Prepares an LDAP telephone number for matching, as described in
+/// [RFC 4518][].
+///
+/// This applies `ldapprep` without its space handling, followed by the
+/// insignificant character handling of Section 2.6.3, which removes all
+/// spaces and hyphens. Dots and parentheses, which are commonly used to
+/// format telephone numbers, are removed as well.
+///
+/// [RFC 4518]: https://tools.ietf.org/html/rfc4518
+#[must_use = "the prepared string must be used"]
+pub fn ldapprep_telephone(s: &str) -> Result<Cow<'_, str>, Error> {
+    if s.is_empty() {
+        return Ok(Cow::Borrowed(s));
+    }
+    let prepared = ldapprep_steps(s, true)?;
+
+    // RFC4518, 2.6.3. telephoneNumber Insignificant Character Handling
+    if !prepared.contains(is_ldap_telephone_insignificant) {
+        return Ok(prepared);
+    }
+    Ok(Cow::Owned(
+        prepared
+            .chars()
+            .filter(|&c| !is_ldap_telephone_insignificant(c))
+            .collect(),
+    ))
+}
+
+fn is_ldap_telephone_insignificant(c: char) -> bool {
+    matches!(c, ' ' | '.' | '(' | ')') || is_ldap_hyphen(c)
+}
+
+// The hyphen characters of RFC4518, 2.6.3.
+fn is_ldap_hyphen(c: char) -> bool {
+    matches!(
+        c,
+        '\u{002D}' | '\u{058A}' | '\u{2010}' | '\u{2011}' | '\u{2212}' | '\u{FE63}' | '\u{FF0D}'
+    )
+}
+
+/// Prepares a nickname with the Nickname profile of the PRECIS framework.
+///
+/// The Nickname profile is defined in [RFC 8266][], and is based on the
+/// FreeformClass of [RFC 8264][]. Spaces are mapped to U+0020, leading and
+/// trailing spaces are removed and inner runs of spaces are collapsed to a
+/// single space, and the result is lowercased and normalized with NFKC. As
+/// required by Section 2.4, these rules are reapplied until the result is
+/// stable. An error is returned if the result is empty.
+///
+/// [RFC 8266]: https://tools.ietf.org/html/rfc8266
+/// [RFC 8264]: https://tools.ietf.org/html/rfc8264
+#[must_use = "the prepared string must be used"]
+pub fn nicknameprep(s: &str) -> Result<Cow<'_, str>, Error> {
+    // fast path for ascii text
+    if is_nickname_ascii(s) {
+        return Ok(Cow::Borrowed(s));
+    }
+
+    // the rules are not idempotent, so they are reapplied up to three more
+    // times until the result is stable
+    let mut prepared = nickname_rules(s);
+    let mut stable = false;
+    for _ in 0..3 {
+        let reapplied = nickname_rules(&prepared);
+        if reapplied == prepared {
+            stable = true;
+            break;
+        }
+        prepared = reapplied;
+    }
+    if !stable {
+        return Err(Error(ErrorKind::Unstable, None));
+    }
+
+    tables::check_non_empty(&prepared)?;
+    if let Some(c) = prepared
+        .chars()
+        .find(|&c| tables::precis_freeform_prohibited(c))
+    {
+        return Err(Error::prohibited(c, None));
+    }
+    tables::check_contextual_rules(&prepared)?;
+
+    if prepared == s {
+        Ok(Cow::Borrowed(s))
+    } else {
+        Ok(Cow::Owned(prepared))
+    }
+}
+
+// Determines if `s` is printable ASCII which is unchanged by the Nickname
+// profile.
+fn is_nickname_ascii(s: &str) -> bool {
+    !s.is_empty()
+        && s.bytes()
+            .all(|b| matches!(b, b' '..=b'~') && !b.is_ascii_uppercase())
+        && !s.starts_with(' ')
+        && !s.ends_with(' ')
+        && !s.contains("  ")
+}
+
+// RFC8266, 2.2., rules 2. to 4.
+fn nickname_rules(s: &str) -> String {
+    // 2. Additional Mapping Rule
+    let mut mapped = String::with_capacity(s.len());
+    for word in s
+        .split(tables::is_unicode_zs_category)
+        .filter(|word| !word.is_empty())
+    {
+        if !mapped.is_empty() {
+            mapped.push(' ');
+        }
+        mapped.push_str(word);
+    }
+
+    // 3. Case Mapping Rule and 4. Normalization Rule
+    mapped.to_lowercase().nfkc().collect()
+}
+
+/// Prepares a string with the OpaqueString profile of the PRECIS framework.
+///
+/// OpaqueString is defined in [RFC 8265][] for passwords and other opaque
+/// strings, replacing SASLprep. Unlike SASLprep, only space characters are
+/// mapped, to U+0020, the result is normalized with NFC rather than NFKC, and
+/// the prohibited characters are those disallowed by the FreeformClass of
+/// [RFC 8264][]. An error is returned if the result is empty.
+///
+/// [RFC 8265]: https://tools.ietf.org/html/rfc8265
+/// [RFC 8264]: https://tools.ietf.org/html/rfc8264
+#[must_use = "the prepared string must be used"]
+pub fn opaque_string(s: &str) -> Result<Cow<'_, str>, Error> {
+    // RFC8265, 4.2.1. Additional Mapping Rule and Normalization Rule,
+    // skipped if there is nothing to map or normalize
+    let prepared = if s.contains(|c| c != ' ' && tables::is_unicode_zs_category(c)) || !is_nfc(s) {
+        Cow::Owned(
+            s.chars()
+                .map(|c| {
+                    if tables::is_unicode_zs_category(c) {
+                        ' '
+                    } else {
+                        c
+                    }
+                })
+                .nfc()
+                .collect(),
+        )
+    } else {
+        Cow::Borrowed(s)
+    };
+
+    tables::check_non_empty(&prepared)?;
+    if let Some(c) = prepared
+        .chars()
+        .find(|&c| tables::precis_freeform_prohibited(c))
+    {
+        return Err(Error::prohibited(c, None));
+    }
+    tables::check_contextual_rules(&prepared)?;
+
+    Ok(prepared)
+}
+
+/// A stringprep profile whose steps can be inspected with `PrepStepIterator`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PrepStepProfile {
+    /// SASLprep, as implemented by `saslprep_stored`.
+    Saslprep,
+    /// Nameprep, as implemented by `nameprep`.
+    Nameprep,
+    /// Nodeprep, as implemented by `nodeprep`.
+    Nodeprep,
+    /// Resourceprep, as implemented by `resourceprep`.
+    Resourceprep,
+}
+
+impl PrepStepProfile {
+    fn map_char(self, c: char, out: &mut Vec<char>) {
+        let mut buf = [0; 4];
+        out.extend(self.profile().map(c.encode_utf8(&mut buf)).chars());
+    }
+
+    fn profile(self) -> &'static dyn Profile {
+        match self {
+            PrepStepProfile::Saslprep => &SaslPrep,
+            PrepStepProfile::Nameprep => &NamePrep,
+            PrepStepProfile::Nodeprep => &NodePrep,
+            PrepStepProfile::Resourceprep => &ResourcePrep,
+        }
+    }
+}
+
+/// Options for preparing strings with a customized version of a stringprep
+/// profile.
+///
+/// Start from one of the standard profiles and disable the checks that aren't
+/// wanted:
+///
+/// ```
+/// use stringprep::PrepOptions;
+///
+/// let prepared = PrepOptions::saslprep()
+///     .without_unassigned_check()
+///     .prepare("I\u{00AD}X\u{0221}")
+///     .unwrap();
+/// assert_eq!(prepared, "IX\u{0221}");
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PrepOptions {
+    profile: PrepStepProfile,
+    check_bidi: bool,
+    check_unassigned: bool,
+}
+
+impl PrepOptions {
+    /// Returns the options used by SASLprep, as implemented by `saslprep_stored`.
+    ///
+    /// Both the bidirectional text and unassigned code point checks are
+    /// enabled.
+    #[must_use]
+    pub fn saslprep() -> PrepOptions {
+        PrepOptions {
+            profile: PrepStepProfile::Saslprep,
+            check_bidi: true,
+            check_unassigned: true,
+        }
+    }
+
+    /// Returns the options used by Nameprep, as implemented by `nameprep`.
+    ///
+    /// Both the bidirectional text and unassigned code point checks are
+    /// enabled.
+    #[must_use]
+    pub fn nameprep() -> PrepOptions {
+        PrepOptions {
+            profile: PrepStepProfile::Nameprep,
+            check_bidi: true,
+            check_unassigned: true,
+        }
+    }
+
+    /// Disables the check for unassigned code points.
+    ///
+    /// RFC 3454 only requires this check for stored strings, and both SASLprep
+    /// and Nameprep allow unassigned code points in queries.
+    #[must_use]
+    pub fn without_unassigned_check(mut self) -> PrepOptions {
+        self.check_unassigned = false;
+        self
+    }
+
+    /// Disables the check for prohibited bidirectional text.
+    ///
+    /// Both SASLprep and Nameprep require this check, so strings prepared
+    /// without it are not valid for either profile.
+    #[must_use]
+    pub fn without_bidi_check(mut self) -> PrepOptions {
+        self.check_bidi = false;
+        self
+    }
+
+    /// Prepares a string with these options.
+    #[must_use = "the prepared string must be used"]
+    pub fn prepare<'a>(&self, s: &'a str) -> Result<Cow<'a, str>, Error> {
+        let profile = WithChecks {
+            profile: self.profile.profile(),
+            check_bidi: self.check_bidi,
+            check_unassigned: self.check_unassigned,
+        };
+        prepare(s, &profile)
+    }
+}
+
+/// The outcome of a single step of the stringprep algorithm, as yielded by
+/// `PrepStepIterator`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum PrepStepResult {
+    /// A character was mapped to another character.
+    ///
+    /// A character mapped to several characters produces one result for each
+    /// of them which differs from the original.
+    Mapped(char, char),
+    /// A character was mapped to nothing.
+    Filtered(char),
+    /// The mapped string was normalized, producing this string.
+    Normalized(String),
+    /// A prohibited character was found in the normalized string.
+    ProhibitedFound(char),
+    /// The normalized string passed the bidirectional text check.
+    BidiOk,
+    /// The normalized string failed the bidirectional text check.
+    BidiError,
+    /// An unassigned code point was found in the normalized string.
+    UnassignedFound(char),
+}
+
+/// An iterator over the steps taken when preparing a string with a stringprep
+/// profile.
+///
+/// Unlike the profile functions, which stop at the first error, every step is
+/// run, so the results form a full audit trail of what happened to the input.
+/// This is intended for debugging and protocol analysis; use the profile
+/// functions to actually prepare strings.
+#[derive(Debug)]
+pub struct PrepStepIterator<'a> {
+    s: &'a str,
+    profile: PrepStepProfile,
+    steps: Option<vec::IntoIter<PrepStepResult>>,
+}
+
+impl<'a> PrepStepIterator<'a> {
+    /// Creates an iterator over the steps taken when preparing `s` with
+    /// `profile`.
+    #[must_use]
+    pub fn new(s: &'a str, profile: PrepStepProfile) -> PrepStepIterator<'a> {
+        PrepStepIterator {
+            s,
+            profile,
+            steps: None,
+        }
+    }
+
+    fn run(&self) -> Vec<PrepStepResult> {
+        let mut steps = vec![];
+
+        // Mapping
+        let mut mapped = String::with_capacity(self.s.len());
+        let mut buf = vec![];
+        for c in self.s.chars() {
+            buf.clear();
+            self.profile.map_char(c, &mut buf);
+            if buf.is_empty() {
+                steps.push(PrepStepResult::Filtered(c));
+            }
+            for &m in &buf {
+                if m != c {
+                    steps.push(PrepStepResult::Mapped(c, m));
+                }
+                mapped.push(m);
+            }
+        }
+
+        // Normalization
+        let normalized = mapped.nfkc().collect::<String>();
+        steps.push(PrepStepResult::Normalized(normalized.clone()));
+
+        // Prohibited Output
+        for c in normalized
+            .chars()
+            .filter(|&c| self.profile.profile().is_prohibited(c))
+        {
+            steps.push(PrepStepResult::ProhibitedFound(c));
+        }
+
+        // Bidirectional Characters
+        if is_prohibited_bidirectional_text(&normalized) {
+            steps.push(PrepStepResult::BidiError);
+        } else {
+            steps.push(PrepStepResult::BidiOk);
+        }
+
+        // Unassigned Code Points
+        for c in normalized
+            .chars()
+            .filter(|&c| tables::unassigned_code_point(c))
+        {
+            steps.push(PrepStepResult::UnassignedFound(c));
+        }
+
+        steps
+    }
+}
+
+impl<'a> Iterator for PrepStepIterator<'a> {
+    type Item = PrepStepResult;
+
+    fn next(&mut self) -> Option<PrepStepResult> {
+        if self.steps.is_none() {
+            self.steps = Some(self.run().into_iter());
+        }
+        self.steps.as_mut().unwrap().next()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use alloc::string::ToString;
+
+    fn assert_prohibited_character<T>(result: Result<T, Error>) {
+        match result {
+            Err(Error(ErrorKind::ProhibitedCharacter(_), _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    fn assert_unassigned_code_point<T>(result: Result<T, Error>) {
+        match result {
+            Err(Error(ErrorKind::UnassignedCodePoint(_), _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    fn assert_starts_with_combining_char<T>(result: Result<T, Error>) {
+        match result {
+            Err(Error(ErrorKind::StartsWithCombiningCharacter, _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn error_kinds() {
+        assert_eq!(
+            saslprep_stored("a\u{0007}").unwrap_err().kind(),
+            &ErrorKind::ProhibitedCharacter('\u{0007}')
+        );
+        assert_eq!(
+            nameprep("\u{0221}").unwrap_err().kind(),
+            &ErrorKind::UnassignedCodePoint('\u{0221}')
+        );
+        assert_eq!(
+            saslprep_stored("\u{0627}a").unwrap_err().kind(),
+            &ErrorKind::ProhibitedBidirectionalText
+        );
+        assert_eq!(
+            x520prep("\u{0301}a", false).unwrap_err().kind(),
+            &ErrorKind::StartsWithCombiningCharacter
+        );
+        assert_eq!(
+            x520prep("", false).unwrap_err().kind(),
+            &ErrorKind::EmptyString
+        );
+        assert_eq!(
+            saslprep_with_max_len("ab", 1).unwrap_err().kind(),
+            &ErrorKind::TooLong(2)
+        );
+        assert_eq!(
+            nameprep_from_ace("xn--a-!").unwrap_err().kind(),
+            &ErrorKind::InvalidPunycode
+        );
+        assert_eq!(
+            tables::check_not_all_spaces("  ").unwrap_err().kind(),
+            &ErrorKind::AllSpaces
+        );
+        let mut buf = [0; 1];
+        assert_eq!(
+            saslprep_short("ab", &mut buf).unwrap_err().kind(),
+            &ErrorKind::OutputTooLarge
+        );
+    }
+
+    // RFC4013, 3. Examples
+    #[test]
+    fn saslprep_examples() {
+        assert_prohibited_character(saslprep_stored("\u{0007}"));
+    }
+
+    #[test]
+    fn saslprep_with_max_len_examples() {
+        assert_eq!(saslprep_with_max_len("I\u{00AD}X", 2).unwrap(), "IX");
+        match saslprep_with_max_len("\u{FB01}", 1) {
+            Err(Error(ErrorKind::TooLong(2), _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn prepped_string() {
+        let s = "user".to_string();
+        let ptr = s.as_ptr();
+        let prepped = PreppedString::try_from(s).unwrap();
+        assert_eq!(prepped.as_ptr(), ptr);
+        assert_eq!(&*prepped, "user");
+
+        let prepped = PreppedString::try_from("I\u{00AD}X".to_string()).unwrap();
+        assert_eq!(prepped.as_str(), "IX");
+        assert_eq!(prepped.to_string(), "IX");
+        assert_eq!(AsRef::<[u8]>::as_ref(&prepped), b"IX");
+        #[cfg(feature = "std")]
+        assert_eq!(AsRef::<Path>::as_ref(&prepped), Path::new("IX"));
+
+        match PreppedString::try_from("a\u{0007}".to_string()) {
+            Err((Error(ErrorKind::ProhibitedCharacter('\u{0007}'), _), s)) => {
+                assert_eq!(s, "a\u{0007}")
+            }
+            _ => panic!(),
+        }
+
+        let trusted = PreppedString::from_trusted("IX".to_string());
+        assert_eq!(trusted, prepped);
+        assert_eq!(String::from(trusted), "IX");
+    }
+
+    #[test]
+    fn saslprep_for_oauth_examples() {
+        assert!(matches!(
+            saslprep_for_oauth("pass_word-1.0*"),
+            Ok(Cow::Borrowed("pass_word-1.0*"))
+        ));
+        assert_eq!(
+            saslprep_for_oauth("a b&c=\u{00A0}\u{00E9}").unwrap(),
+            "a+b%26c%3D+%C3%A9"
+        );
+        assert_prohibited_character(saslprep_for_oauth("\u{0007}"));
+    }
+
+    #[test]
+    fn saslprep_for_basic_auth_examples() {
+        assert_eq!(saslprep_for_basic_auth("I\u{00AD}X").unwrap(), "IX");
+        assert_eq!(saslprep_for_basic_auth("a:b").unwrap(), "a:b");
+        assert_prohibited_character(saslprep_for_basic_auth("\u{0007}"));
+    }
+
+    #[test]
+    fn saslprep_for_ldap_bind_examples() {
+        assert_eq!(saslprep_for_ldap_bind("I\u{00AD}X").unwrap(), "IX");
+        assert_prohibited_character(saslprep_for_ldap_bind("\u{0007}"));
+        for s in &["", "\u{00AD}\u{FEFF}"] {
+            match saslprep_for_ldap_bind(s) {
+                Err(Error(ErrorKind::EmptyString, _)) => (),
+                _ => panic!(),
+            }
+        }
+    }
+
+    #[test]
+    fn saslprep_concat_channel_binding_examples() {
+        assert_eq!(
+            saslprep_concat_channel_binding("I\u{00AD}X", b"\x01\x02").unwrap(),
+            b"IX\x01\x02"
+        );
+        assert_prohibited_character(saslprep_concat_channel_binding("\u{0007}", b""));
+    }
+
+    #[cfg(feature = "debug")]
+    #[test]
+    fn saslprep_debug_examples() {
+        let debug = saslprep_debug("A\u{00A0}\u{00AD}\u{0007}");
+        assert!(debug.contains("U+0041 'A' \u{2192} unchanged"));
+        assert!(debug.contains("\u{2192} mapped to U+0020 ' ' (C.1.2)"));
+        assert!(debug.contains("\u{2192} mapped to nothing (B.1)"));
+        assert!(debug.contains("\u{2192} prohibited (C.2.1 ASCII control character)"));
+    }
+
+    #[test]
+    fn saslprep_chunked() {
+        let mut chunked = SaslPrepChunked::new();
+        for chunk in &["I\u{00AD}", "\u{00A0}", "", "\u{2168}e", "\u{0301}"] {
+            chunked.feed(chunk).unwrap();
+        }
+        assert_eq!(chunked.finish().unwrap(), "I IX\u{00E9}");
+
+        let mut chunked = SaslPrepChunked::new();
+        chunked.feed("abc").unwrap();
+        assert_prohibited_character(chunked.feed("d\u{0007}"));
+
+        let mut chunked = SaslPrepChunked::new();
+        chunked.feed("\u{0627}").unwrap();
+        chunked.feed("a").unwrap();
+        match chunked.finish() {
+            Err(Error(ErrorKind::ProhibitedBidirectionalText, _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn saslprep_short_examples() {
+        let mut buf = [0; 8];
+        assert_eq!(saslprep_short("I\u{00AD}X", &mut buf).unwrap(), "IX");
+        assert_eq!(saslprep_short("\u{2168}", &mut buf).unwrap(), "IX");
+        assert_prohibited_character(saslprep_short("a\u{0007}", &mut buf));
+        match saslprep_short("too long for buf", &mut buf) {
+            Err(Error(ErrorKind::OutputTooLarge, _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn prep_step_iterator() {
+        let steps = PrepStepIterator::new("I\u{00AD}\u{00A0}X\u{0007}", PrepStepProfile::Saslprep)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                PrepStepResult::Filtered('\u{00AD}'),
+                PrepStepResult::Mapped('\u{00A0}', ' '),
+                PrepStepResult::Normalized("I X\u{0007}".to_string()),
+                PrepStepResult::ProhibitedFound('\u{0007}'),
+                PrepStepResult::BidiOk,
+            ]
+        );
+
+        let steps = PrepStepIterator::new("\u{0627}A\u{0221}", PrepStepProfile::Nameprep)
+            .collect::<Vec<_>>();
+        assert_eq!(
+            steps,
+            [
+                PrepStepResult::Mapped('A', 'a'),
+                PrepStepResult::Normalized("\u{0627}a\u{0221}".to_string()),
+                PrepStepResult::BidiError,
+                PrepStepResult::UnassignedFound('\u{0221}'),
+            ]
+        );
+    }
+
+    #[test]
+    fn profile() {
+        struct CaseSensitiveNfc;
+
+        impl Profile for CaseSensitiveNfc {
+            fn map_char(&self, c: char) -> Option<char> {
+                tables::map_char_saslprep(c)
+            }
+
+            fn is_prohibited(&self, c: char) -> bool {
+                tables::ascii_control_character(c)
+            }
+
+            fn normalization(&self) -> Normalization {
+                Normalization::Nfc
+            }
+
+            fn check_bidi(&self) -> bool {
+                false
+            }
+        }
+
+        assert_eq!(prepare("user", &CaseSensitiveNfc).unwrap(), "user");
+        assert_eq!(prepare("I\u{00AD}X", &CaseSensitiveNfc).unwrap(), "IX");
+        // NFC leaves compatibility characters alone
+        assert_eq!(prepare("\u{2168}", &CaseSensitiveNfc).unwrap(), "\u{2168}");
+        assert_eq!(
+            prepare("\u{0627}a", &CaseSensitiveNfc).unwrap(),
+            "\u{0627}a"
+        );
+        assert_prohibited_character(prepare("a\u{0007}", &CaseSensitiveNfc));
+        assert_unassigned_code_point(prepare("\u{0221}", &CaseSensitiveNfc));
+
+        assert_eq!(prepare("\u{2168}", &SaslPrep).unwrap(), "IX");
+        assert_eq!(prepare("Stra\u{00DF}e", &NamePrep).unwrap(), "strasse");
+        assert_eq!(NamePrep.map_char('A'), Some('a'));
+        assert_eq!(NamePrep.map_char('\u{00AD}'), None);
+        assert_eq!(NamePrep.map_char('\u{00DF}'), Some('\u{00DF}'));
+        assert_eq!(ResourcePrep.map_char('A'), Some('A'));
+    }
+
+    #[test]
+    fn prep_options() {
+        for s in &[
+            "user",
+            "I\u{00AD}X",
+            "\u{00A0}a",
+            "\u{2168}",
+            "\u{0627}1\u{0628}",
+        ] {
+            assert_eq!(
+                PrepOptions::saslprep().prepare(s).unwrap(),
+                saslprep_stored(s).unwrap()
+            );
+        }
+        for s in &["Example", "Stra\u{00DF}e", "\u{0627}1\u{0628}"] {
+            assert_eq!(
+                PrepOptions::nameprep().prepare(s).unwrap(),
+                nameprep(s).unwrap()
+            );
+        }
+
+        assert_unassigned_code_point(PrepOptions::saslprep().prepare("\u{0221}"));
+        assert_eq!(
+            PrepOptions::saslprep()
+                .without_unassigned_check()
+                .prepare("\u{0221}")
+                .unwrap(),
+            "\u{0221}"
+        );
+
+        match PrepOptions::nameprep().prepare("\u{0627}a") {
+            Err(Error(ErrorKind::ProhibitedBidirectionalText, _)) => (),
+            _ => panic!(),
+        }
+        assert_eq!(
+            PrepOptions::nameprep()
+                .without_bidi_check()
+                .prepare("\u{0627}A")
+                .unwrap(),
+            "\u{0627}a"
+        );
+        assert_prohibited_character(
+            PrepOptions::nameprep()
+                .without_bidi_check()
+                .prepare("\u{E000}"),
+        );
+    }
+
+    #[test]
+    fn nameprep_ace_examples() {
+        assert_eq!(nameprep_to_ascii("example").unwrap(), "example");
+        assert_eq!(
+            nameprep_to_ascii("R\u{00E4}ksm\u{00F6}rg\u{00E5}s").unwrap(),
+            "xn--rksmrgs-5wao1o"
+        );
+        assert_eq!(
+            nameprep_from_ace("XN--rksmrgs-5wao1o").unwrap(),
+            "r\u{00E4}ksm\u{00F6}rg\u{00E5}s"
+        );
+        assert_eq!(nameprep_from_ace("EXAMPLE").unwrap(), "example");
+        assert!(nameprep_to_ascii(&"a".repeat(63)).is_ok());
+        match nameprep_to_ascii(&"a".repeat(64)) {
+            Err(Error(ErrorKind::TooLong(64), _)) => (),
+            _ => panic!(),
+        }
+        let label = (0..20)
+            .map(|i| char::from_u32(0x4E00 + i * 0x1F3).unwrap())
+            .collect::<String>();
+        match nameprep_to_ascii(&label) {
+            Err(Error(ErrorKind::TooLong(64), _)) => (),
+            _ => panic!(),
+        }
+        let label = (0..40_000)
+            .map(|i| char::from_u32(0x4E00 + i % 0x5000).unwrap())
+            .collect::<String>();
+        match nameprep_to_ascii(&label) {
+            Err(Error(ErrorKind::TooLong(40_004), _)) => (),
+            _ => panic!(),
+        }
+        let label = "\u{4E00}".repeat(22);
+        assert_eq!(label.len(), 66);
+        assert!(nameprep_to_ascii(&label).is_ok());
+        match nameprep_from_ace("xn--a-!") {
+            Err(Error(ErrorKind::InvalidPunycode, _)) => (),
+            _ => panic!(),
+        }
+
+        // RFC3490, 4.1., steps 5. and 8.
+        assert_eq!(
+            nameprep_to_ascii("xn--\u{00E9}").unwrap_err().kind(),
+            &ErrorKind::InvalidAce
+        );
+        assert_eq!(
+            nameprep_to_ascii("XN--\u{00E9}").unwrap_err().kind(),
+            &ErrorKind::InvalidAce
+        );
+        assert_eq!(
+            nameprep_to_ascii("").unwrap_err().kind(),
+            &ErrorKind::EmptyString
+        );
+        assert_eq!(
+            nameprep_to_ascii("\u{00AD}").unwrap_err().kind(),
+            &ErrorKind::EmptyString
+        );
+        // an ASCII label with the prefix is left alone
+        assert_eq!(nameprep_to_ascii("xn--abc").unwrap(), "xn--abc");
+
+        // RFC3490, 4.2., steps 6. and 7.
+        // the comparison ignores ASCII case
+        assert_eq!(
+            nameprep_from_ace("xn--Rksmrgs-5WAO1O").unwrap(),
+            "r\u{00E4}ksm\u{00F6}rg\u{00E5}s"
+        );
+        for ace in &[
+            "xn--",
+            "XN--",
+            // decodes to an ASCII label
+            "xn--abc-",
+            // decodes to a label containing a character mapped to nothing
+            "xn--ab-5da5v",
+        ] {
+            assert_eq!(
+                nameprep_from_ace(ace).unwrap_err().kind(),
+                &ErrorKind::InvalidAce,
+                "{:?}",
+                ace
+            );
+        }
+    }
+
+    #[test]
+    fn nodeprep_examples() {
+        assert_prohibited_character(nodeprep(" "));
+        assert_prohibited_character(nodeprep("\u{00a0}"));
+        assert_prohibited_character(nodeprep("foo@bar"));
+    }
+
+    #[test]
+    fn nodeprep_prohibited_ascii() {
+        for c in "\"&'/:<>@".chars() {
+            assert!(tables::nodeprep_prohibited_ascii(c));
+            assert_prohibited_character(nodeprep(&format!("a{}b", c)));
+        }
+        assert!(!tables::nodeprep_prohibited_ascii('a'));
+        assert!(matches!(nodeprep("juliet"), Ok(Cow::Borrowed("juliet"))));
+        assert_eq!(nodeprep("Juliet\u{00AD}").unwrap(), "juliet");
+    }
+
+    #[test]
+    fn resourceprep_examples() {
+        assert_eq!("foo@bar", resourceprep("foo@bar").unwrap());
+        assert!(matches!(
+            resourceprep("The Orchard"),
+            Ok(Cow::Borrowed("The Orchard"))
+        ));
+        assert_eq!(
+            resourceprep("Balcony\u{00A0}Scene").unwrap(),
+            "Balcony Scene"
+        );
+        assert_eq!(resourceprep("Balcony\u{00AD}").unwrap(), "Balcony");
+        assert_eq!(
+            resourceprep("\u{05D0} \u{05D1}").unwrap(),
+            "\u{05D0} \u{05D1}"
+        );
+        assert_eq!(
+            resourceprep("\u{05D0}a").unwrap_err().kind(),
+            &ErrorKind::ProhibitedBidirectionalText
+        );
+        assert_prohibited_character(resourceprep("a\u{0007}"));
+    }
+
+    #[test]
+    fn x520prep_examples() {
+        assert_eq!(x520prep("foo@bar", true).unwrap(), "foo@bar");
+        assert_eq!(
+            x520prep("J.\u{FE00} \u{9}W. \u{B}wuz h\u{0115}re", false).unwrap(),
+            "J.  W.  wuz h\u{0115}re"
+        );
+        assert_eq!(
+            x520prep("J.\u{FE00} \u{9}W. \u{B}wuz h\u{0115}re", true).unwrap(),
+            "j.  w.  wuz h\u{0115}re"
+        );
+        assert_eq!(x520prep("UPPERCASED", true).unwrap(), "uppercased");
+        assert_starts_with_combining_char(x520prep("\u{0306}hello", true));
+    }
+
+    #[test]
+    fn ldapprep_examples() {
+        assert_eq!(ldapprep("  Barbara  JENSEN ").unwrap(), " barbara  jensen ");
+        assert_eq!(ldapprep("Babs\u{00AD}").unwrap(), " babs ");
+        assert_eq!(ldapprep("").unwrap(), "  ");
+        assert_eq!(ldapprep("   ").unwrap(), "  ");
+        assert_eq!(
+            ldapprep_case_exact("Barbara\u{3000}Jensen").unwrap(),
+            " Barbara  Jensen "
+        );
+        assert_starts_with_combining_char(ldapprep("\u{0306}hello"));
+
+        // both matching rules normalize with NFKC
+        assert_eq!(ldapprep("\u{2168}").unwrap(), " ix ");
+        assert_eq!(ldapprep_case_exact("\u{2168}").unwrap(), " IX ");
+        assert_eq!(ldapprep("e\u{0301}").unwrap(), " \u{00E9} ");
+        assert_eq!(ldapprep_case_exact("e\u{0301}").unwrap(), " \u{00E9} ");
+
+        // table C.8
+        assert_prohibited_character(ldapprep("a\u{200E}b"));
+        assert_prohibited_character(ldapprep_case_exact("a\u{202A}b"));
+        assert_prohibited_character(ldapprep_telephone("555\u{200E}1234"));
+
+        assert_eq!(
+            ldapprep_telephone("+1 (408) 555-4798").unwrap(),
+            "+14085554798"
+        );
+        assert_eq!(
+            ldapprep_telephone("+1 (408) 555.4798").unwrap(),
+            "+14085554798"
+        );
+        assert_eq!(ldapprep_telephone("\u{FF11}\u{FF12}").unwrap(), "12");
+        assert_eq!(
+            ldapprep_telephone("555\u{2212}4798\u{FF0D}1").unwrap(),
+            "55547981"
+        );
+        assert_eq!(ldapprep_telephone("+15554798").unwrap(), "+15554798");
+    }
+
+    #[test]
+    fn non_character_code_points() {
+        let mut non_characters = ('\u{FDD0}'..='\u{FDEF}').collect::<Vec<_>>();
+        for plane in 0..=0x10 {
+            for low in [0xFFFE, 0xFFFF] {
+                non_characters.push(char::from_u32(plane << 16 | low).unwrap());
+            }
+        }
+        assert_eq!(non_characters.len(), 66);
+        for &c in &non_characters {
+            assert!(tables::non_character_code_point(c));
+        }
+
+        let count = (0..=0x10FFFF)
+            .filter_map(char::from_u32)
+            .filter(|&c| tables::non_character_code_point(c))
+            .count();
+        assert_eq!(count, 66);
     }
 
     #[test]
-    fn x520prep_examples() {
-        assert_eq!(x520prep("foo@bar", true).unwrap(), "foo@bar");
+    fn prohibited_by() {
+        assert_eq!(tables::prohibited_by('a'), None);
+        assert_eq!(tables::prohibited_by(' '), None);
+        assert_eq!(tables::prohibited_by('\u{00A0}'), Some("C.1.2"));
+        assert_eq!(tables::prohibited_by('\u{0007}'), Some("C.2.1"));
+        assert_eq!(tables::prohibited_by('\u{0085}'), Some("C.2.2"));
+        assert_eq!(tables::prohibited_by('\u{E000}'), Some("C.3"));
+        assert_eq!(tables::prohibited_by('\u{FDD0}'), Some("C.4"));
+        assert_eq!(tables::prohibited_by('\u{FFFD}'), Some("C.6"));
+        assert_eq!(tables::prohibited_by('\u{2FF0}'), Some("C.7"));
+        assert_eq!(tables::prohibited_by('\u{202E}'), Some("C.8"));
+        assert_eq!(tables::prohibited_by('\u{E0001}'), Some("C.9"));
+        assert_eq!(tables::prohibited_by('\u{0221}'), Some("A.1"));
+
         assert_eq!(
-            x520prep("J.\u{FE00} \u{9}W. \u{B}wuz h\u{0115}re", false).unwrap(),
-            "J.  W.  wuz h\u{0115}re"
+            saslprep_stored("a\u{0007}").unwrap_err().to_string(),
+            "prohibited character `\u{0007}` (RFC 3454 table C.2.1)"
         );
         assert_eq!(
-            x520prep("J.\u{FE00} \u{9}W. \u{B}wuz h\u{0115}re", true).unwrap(),
-            "j.  w.  wuz h\u{0115}re"
+            nodeprep("a@b").unwrap_err().to_string(),
+            "prohibited character `@`"
+        );
+        assert_eq!(
+            nodeprep("a b").unwrap_err().to_string(),
+            "prohibited character ` ` (RFC 3454 table C.1.1)"
+        );
+        assert_eq!(
+            nameprep("a\u{2FF0}").unwrap_err().to_string(),
+            "prohibited character `\u{2FF0}` (RFC 3454 table C.7)"
+        );
+        assert_eq!(
+            opaque_string("a\tb").unwrap_err().to_string(),
+            "prohibited character `\t`"
+        );
+    }
+
+    #[test]
+    fn tagging_characters() {
+        assert!(tables::tagging_character('\u{E0001}'));
+        for c in '\u{E0020}'..='\u{E007F}' {
+            assert!(tables::tagging_character(c));
+            assert!(!tables::private_use(c));
+        }
+        assert!(!tables::tagging_character('\u{E0000}'));
+        assert!(!tables::tagging_character('\u{E0002}'));
+        assert!(!tables::tagging_character('\u{E001F}'));
+        assert!(!tables::tagging_character('\u{E0080}'));
+    }
+
+    #[test]
+    fn commonly_mapped_to_nothing_chars() {
+        let chars = tables::commonly_mapped_to_nothing_chars().collect::<Vec<_>>();
+        let expected = (0..=0x10FFFF)
+            .filter_map(char::from_u32)
+            .filter(|&c| tables::commonly_mapped_to_nothing(c))
+            .collect::<Vec<_>>();
+        assert_eq!(chars.len(), 27);
+        assert_eq!(chars, expected);
+    }
+
+    #[test]
+    fn in_b1_and_c_table() {
+        let chars = tables::commonly_mapped_to_nothing_chars()
+            .filter(|&c| tables::is_in_b1_and_c_table(c))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            chars,
+            ['\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}']
+        );
+        assert_eq!(nameprep("a\u{200D}b").unwrap(), "ab");
+    }
+
+    #[test]
+    fn nameprep_ascii_labels() {
+        assert!(tables::is_all_ascii_label_chars("Example-1"));
+        assert!(!tables::is_all_ascii_label_chars("a_b"));
+        assert!(!tables::is_ascii_label_char('.'));
+        assert_eq!(nameprep("Example-1").unwrap(), "example-1");
+        assert_eq!(nameprep("XN--ABC").unwrap(), "xn--abc");
+        assert_eq!(nameprep("example").unwrap(), "example");
+    }
+
+    #[test]
+    fn default_ignorable() {
+        for &c in &['\u{00AD}', '\u{200D}', '\u{2064}', '\u{3164}', '\u{E0FFF}'] {
+            assert!(tables::is_default_ignorable(c));
+        }
+        assert!(!tables::is_default_ignorable('a'));
+        assert!(!tables::is_default_ignorable(' '));
+        assert!(!tables::is_default_ignorable('\u{E1000}'));
+
+        for &c in &[
+            '\u{115F}', '\u{1160}', '\u{17B4}', '\u{17B5}', '\u{3164}', '\u{FFA0}',
+        ] {
+            assert!(tables::is_default_ignorable(c));
+            assert!(saslprep_stored(&c.to_string()).is_ok());
+        }
+    }
+
+    #[test]
+    fn invisible_format_char() {
+        for &c in &['\u{200B}', '\u{FE0F}', '\u{2062}', '\u{202E}', '\u{E0041}'] {
+            assert!(tables::is_invisible_format_char(c));
+        }
+        for &c in &['a', ' ', '\u{00A0}', '\u{E000}'] {
+            assert!(!tables::is_invisible_format_char(c));
+        }
+    }
+
+    #[test]
+    fn soft_hyphen() {
+        assert!(tables::is_soft_hyphen('\u{00AD}'));
+        assert!(!tables::is_soft_hyphen('-'));
+        assert!(tables::commonly_mapped_to_nothing(tables::SOFT_HYPHEN));
+        assert!(!tables::change_display_properties_or_deprecated(
+            tables::SOFT_HYPHEN
+        ));
+    }
+
+    #[test]
+    fn apply_case_fold() {
+        assert_eq!(tables::apply_case_fold("Stra\u{00DF}E"), "strasse");
+        if let Cow::Owned(_) = tables::apply_case_fold("lowercase") {
+            panic!("“lowercase” should not be reallocated");
+        }
+    }
+
+    #[test]
+    fn remove_b1_chars() {
+        assert_eq!(tables::remove_b1_chars("I\u{00AD}X\u{FEFF}"), "IX");
+        if let Cow::Owned(_) = tables::remove_b1_chars("password") {
+            panic!("“password” should not be reallocated");
+        }
+    }
+
+    #[test]
+    fn encoded_surrogates() {
+        // U+1F980 as a CESU-8 surrogate pair
+        let pair = b"a\xED\xA0\xBE\xED\xB6\x80b";
+        assert!(!tables::is_utf16_surrogate_in_bytes(pair));
+        assert!(tables::is_wtf8_surrogate(pair));
+        assert!(tables::is_utf16_surrogate_in_bytes(b"a\xED\xA0\xBEb"));
+        assert!(tables::is_utf16_surrogate_in_bytes(b"a\xED\xB6\x80b"));
+        assert!(!tables::is_wtf8_surrogate("a\u{1F980}\u{D7FF}".as_bytes()));
+    }
+
+    #[test]
+    fn pvalid_for_dns() {
+        for &c in &[
+            'a', '1', '-', '\u{00DF}', '\u{03C2}', '\u{00E9}', '\u{13A0}', '\u{0301}',
+        ] {
+            assert!(tables::is_pvalid_for_dns(c), "U+{:04X}", c as u32);
+        }
+        for &c in &[
+            'A', ' ', '.', '\u{017F}', '\u{00B7}', '\u{0640}', '\u{200D}', '\u{1F80}', '\u{AB70}',
+            '\u{1100}', '\u{20D0}', '\u{E000}', '\u{FFFF}', '\u{2168}',
+        ] {
+            assert!(!tables::is_pvalid_for_dns(c), "U+{:04X}", c as u32);
+        }
+    }
+
+    #[test]
+    fn c8_code_points() {
+        let expected = (0..=0x10FFFF)
+            .filter_map(char::from_u32)
+            .filter(|&c| tables::is_change_display_properties(c) || tables::is_deprecated_format(c))
+            .collect::<Vec<_>>();
+        assert_eq!(tables::C8_CODE_POINTS[..], expected[..]);
+    }
+
+    #[test]
+    fn inappropriate_for_plain_text() {
+        for c in '\u{FFF9}'..='\u{FFFD}' {
+            assert!(tables::inappropriate_for_plain_text(c));
+        }
+        for &c in &['\u{FFF8}', '\u{FFFE}', '\u{FFFF}'] {
+            assert!(!tables::inappropriate_for_plain_text(c));
+        }
+        assert!(tables::non_character_code_point('\u{FFFE}'));
+        assert!(tables::non_character_code_point('\u{FFFF}'));
+    }
+
+    #[test]
+    fn arabic_presentation_forms() {
+        for &c in &['\u{FB50}', '\u{FDF2}', '\u{FE8D}', '\u{FEFB}'] {
+            assert!(tables::is_arabic_presentation_form(c));
+            assert!(!tables::inappropriate_for_canonical_representation(c));
+        }
+        assert!(!tables::is_arabic_presentation_form('\u{0627}'));
+
+        assert_eq!(saslprep_stored("\u{FE8D}").unwrap(), "\u{0627}");
+        assert_eq!(saslprep_stored("\u{FEFB}").unwrap(), "\u{0644}\u{0627}");
+        assert_eq!(nameprep("\u{FB50}").unwrap(), "\u{0671}");
+    }
+
+    #[test]
+    fn inappropriate_for_canonical_representation() {
+        for c in '\u{2FF0}'..='\u{2FFB}' {
+            assert!(tables::inappropriate_for_canonical_representation(c));
+        }
+        assert!(!tables::inappropriate_for_canonical_representation(
+            '\u{2FEF}'
+        ));
+        assert!(!tables::inappropriate_for_canonical_representation(
+            '\u{2FFC}'
+        ));
+    }
+
+    #[test]
+    fn unassigned_range_check() {
+        assert!(tables::unassigned_range_check('\u{0221}', '\u{0221}'));
+        assert!(tables::unassigned_range_check('\u{0234}', '\u{024F}'));
+        assert!(tables::unassigned_range_check('\u{E0080}', '\u{EFFFD}'));
+        assert!(!tables::unassigned_range_check('\u{0220}', '\u{0221}'));
+        assert!(!tables::unassigned_range_check('\u{0221}', '\u{0234}'));
+        assert!(!tables::unassigned_range_check('a', 'z'));
+        assert!(tables::unassigned_range_check('z', 'a'));
+        for w in rfc3454::A_1.windows(2) {
+            assert!(w[0].1 as u32 + 1 < w[1].0 as u32);
+        }
+    }
+
+    #[test]
+    fn check_no_unassigned() {
+        assert!(tables::check_no_unassigned("a\u{0220}").is_ok());
+        match tables::check_no_unassigned("a\u{0221}") {
+            Err(Error(ErrorKind::UnassignedCodePoint('\u{0221}'), _)) => (),
+            _ => panic!(),
+        }
+        assert_unassigned_code_point(saslprep_stored("\u{0221}"));
+        assert_unassigned_code_point(nameprep("\u{0221}"));
+        assert_unassigned_code_point(nodeprep("\u{0221}"));
+        assert_unassigned_code_point(resourceprep("\u{0221}"));
+        // the check runs on the mapped and normalized string
+        assert_unassigned_code_point(saslprep_stored("a\u{00AD}\u{0221}"));
+        assert_eq!(
+            saslprep_stored("\u{0221}").unwrap_err().to_string(),
+            "unassigned code point `\u{0221}`"
+        );
+    }
+
+    #[test]
+    fn prepared_eq() {
+        let inputs = [
+            "user",
+            "USER",
+            "I\u{00AD}X",
+            "IX",
+            "\u{2168}",
+            "a\u{00A0}b",
+            "a b",
+            "\u{00C5}",
+            "A\u{030A}",
+            "\u{212B}",
+            "Stra\u{00DF}e",
+            "strasse",
+            "\u{0627}1\u{0628}",
+        ];
+        for a in &inputs {
+            for b in &inputs {
+                assert_eq!(
+                    saslprep_eq(a, b).unwrap(),
+                    saslprep_stored(a).unwrap() == saslprep_stored(b).unwrap(),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+                assert_eq!(
+                    nameprep_eq(a, b).unwrap(),
+                    nameprep(a).unwrap() == nameprep(b).unwrap(),
+                    "{:?} {:?}",
+                    a,
+                    b
+                );
+            }
+        }
+
+        assert!(saslprep_eq("I\u{00AD}X", "\u{2168}").unwrap());
+        assert!(!saslprep_eq("user", "USER").unwrap());
+        assert!(nameprep_eq("Stra\u{00DF}e", "strasse").unwrap());
+
+        assert_eq!(
+            saslprep_eq("user", "a\u{0007}").unwrap_err().kind(),
+            saslprep_stored("a\u{0007}").unwrap_err().kind()
+        );
+        assert_eq!(
+            saslprep_eq("\u{0627}a", "user").unwrap_err().kind(),
+            saslprep_stored("\u{0627}a").unwrap_err().kind()
+        );
+        assert_eq!(
+            saslprep_eq("user", "\u{0627}1").unwrap_err().kind(),
+            saslprep_stored("\u{0627}1").unwrap_err().kind()
+        );
+        assert_eq!(
+            saslprep_eq("\u{0221}\u{0007}", "user").unwrap_err().kind(),
+            saslprep_stored("\u{0221}\u{0007}").unwrap_err().kind()
+        );
+        assert_unassigned_code_point(saslprep_eq("\u{0221}", "user"));
+        assert_unassigned_code_point(nameprep_eq("example", "\u{0221}"));
+        assert_prohibited_character(nameprep_eq("example", "a\u{E000}b"));
+    }
+
+    #[test]
+    fn prepared_string() {
+        use std::collections::HashSet;
+
+        let prepared = SaslPrep::prepare("I\u{00AD}X").unwrap();
+        assert_eq!(prepared, SaslPrep::prepare("\u{2168}").unwrap());
+        assert_ne!(prepared, SaslPrep::prepare("ix").unwrap());
+        assert_eq!(prepared.as_str(), "IX");
+        assert_eq!(prepared.to_string(), "IX");
+        assert_eq!(AsRef::<[u8]>::as_ref(&prepared), b"IX");
+        #[cfg(feature = "std")]
+        assert_eq!(AsRef::<Path>::as_ref(&prepared), Path::new("IX"));
+        assert_eq!(String::from(prepared.clone()), "IX");
+
+        let mut set = HashSet::new();
+        set.insert(prepared);
+        assert!(set.contains("IX"));
+
+        assert_eq!(NamePrep::prepare("Example").unwrap().as_str(), "example");
+        assert_eq!(NodePrep::prepare("Juliet").unwrap().as_str(), "juliet");
+        assert_eq!(
+            ResourcePrep::prepare("Balcony").unwrap().as_str(),
+            "Balcony"
+        );
+        assert_prohibited_character(SaslPrep::prepare("a\u{0007}"));
+        assert_unassigned_code_point(SaslPrep::prepare("\u{0221}"));
+        assert_eq!(
+            PreparedString::<NamePrep>::from_trusted("example".to_string()).into_string(),
+            "example"
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert!(saslprep_validate("user").is_empty());
+        assert!(saslprep_validate("I\u{00AD}X").is_empty());
+        assert!(nameprep_validate("Example").is_empty());
+
+        let errors = saslprep_validate("a\u{0007}b\u{007F}c\u{E000}");
+        assert_eq!(
+            errors.iter().map(Error::kind).collect::<Vec<_>>(),
+            [
+                &ErrorKind::ProhibitedCharacter('\u{0007}'),
+                &ErrorKind::ProhibitedCharacter('\u{007F}'),
+                &ErrorKind::ProhibitedCharacter('\u{E000}'),
+            ]
+        );
+
+        let errors = saslprep_validate("\u{0627}\u{0007}a\u{007F}\u{E000}");
+        assert_eq!(
+            errors.iter().map(Error::kind).collect::<Vec<_>>(),
+            [
+                &ErrorKind::ProhibitedCharacter('\u{0007}'),
+                &ErrorKind::ProhibitedCharacter('\u{007F}'),
+                &ErrorKind::ProhibitedCharacter('\u{E000}'),
+                &ErrorKind::ProhibitedBidirectionalText,
+            ]
+        );
+
+        let errors = nameprep_validate("\u{0221}\u{E000}\u{0221}");
+        assert_eq!(
+            errors.iter().map(Error::kind).collect::<Vec<_>>(),
+            [
+                &ErrorKind::ProhibitedCharacter('\u{E000}'),
+                &ErrorKind::UnassignedCodePoint('\u{0221}'),
+                &ErrorKind::UnassignedCodePoint('\u{0221}'),
+            ]
+        );
+    }
+
+    #[test]
+    fn nameprep_domain_labels() {
+        assert_eq!(
+            nameprep_domain("\u{C608}\u{C81C}.example.\u{0440}\u{0444}").unwrap(),
+            "\u{C608}\u{C81C}.example.\u{0440}\u{0444}"
+        );
+        assert_eq!(
+            nameprep_domain("M\u{00FC}nchen.Example.COM").unwrap(),
+            "m\u{00FC}nchen.example.com"
+        );
+        assert!(matches!(
+            nameprep_domain("example.com.").unwrap(),
+            Cow::Borrowed("example.com.")
+        ));
+        assert_eq!(
+            nameprep_domain("Example\u{3002}com\u{FF0E}").unwrap(),
+            "example.com."
+        );
+
+        // the bidirectional text check applies to each label separately
+        assert_eq!(
+            nameprep_domain("\u{0627}\u{0628}.example").unwrap(),
+            "\u{0627}\u{0628}.example"
+        );
+        assert!(nameprep("\u{0627}\u{0628}.example").is_err());
+        assert_eq!(
+            nameprep_domain("\u{0627}a.example").unwrap_err().kind(),
+            &ErrorKind::ProhibitedBidirectionalText
+        );
+
+        assert_eq!(
+            nameprep_domain("a\u{2488}b.com").unwrap_err().kind(),
+            &ErrorKind::ProhibitedCharacter('.')
+        );
+        assert_prohibited_character(nameprep_domain("example\u{FE52}com"));
+
+        for s in &["", ".", "example..com", ".example.com", "example.com.."] {
+            assert_eq!(
+                nameprep_domain(s).unwrap_err().kind(),
+                &ErrorKind::EmptyString,
+                "{:?}",
+                s
+            );
+        }
+    }
+
+    #[test]
+    fn nicknameprep_profile() {
+        assert!(matches!(
+            nicknameprep("foo bar").unwrap(),
+            Cow::Borrowed("foo bar")
+        ));
+        assert!(matches!(
+            nicknameprep("\u{0161}\u{00E9}f").unwrap(),
+            Cow::Borrowed(_)
+        ));
+        assert_eq!(nicknameprep("Foo").unwrap(), "foo");
+        assert_eq!(nicknameprep("  Foo   Bar  ").unwrap(), "foo bar");
+        assert_eq!(
+            nicknameprep("\u{3000}Foo\u{00A0}\u{2003}Bar\u{3000}").unwrap(),
+            "foo bar"
+        );
+        assert_eq!(nicknameprep("\u{03A3}").unwrap(), "\u{03C3}");
+        assert_eq!(nicknameprep("\u{2163}").unwrap(), "iv");
+        // NFKC produces an uppercase letter, which is lowercased when the
+        // rules are reapplied
+        assert_eq!(nicknameprep("\u{03D4}").unwrap(), "\u{03CB}");
+
+        for s in &["", " ", "   ", "\u{3000}", "\u{00A0} \u{2003}"] {
+            assert_eq!(
+                nicknameprep(s).unwrap_err().kind(),
+                &ErrorKind::EmptyString,
+                "{:?}",
+                s
+            );
+        }
+
+        assert_prohibited_character(nicknameprep("a\tb"));
+        assert_prohibited_character(nicknameprep("a\u{0007}"));
+        assert_prohibited_character(nicknameprep("a\u{E000}"));
+        assert_prohibited_character(nicknameprep("a\u{2028}b"));
+        assert_prohibited_character(nicknameprep("foo\u{200D}"));
+        // context rule and FreeformClass failures don't come from RFC 3454
+        assert_eq!(
+            nicknameprep("a\u{200D}b").unwrap_err().to_string(),
+            "prohibited character `\u{200D}`"
+        );
+        assert_eq!(
+            nicknameprep("a\u{2028}b").unwrap_err().to_string(),
+            "prohibited character `\u{2028}`"
+        );
+        assert_prohibited_character(nicknameprep("\u{30FB}"));
+        assert_eq!(nicknameprep("Col\u{00B7}legi").unwrap(), "col\u{00B7}legi");
+        assert_eq!(
+            nicknameprep("\u{30A2}\u{30FB}\u{30A4}").unwrap(),
+            "\u{30A2}\u{30FB}\u{30A4}"
+        );
+    }
+
+    #[test]
+    fn precis_freeform_prohibited() {
+        for &c in &[
+            'a',
+            'A',
+            ' ',
+            '~',
+            '\u{00DF}',
+            '\u{00B7}',
+            '\u{200D}',
+            '\u{2163}',
+            '\u{20AC}',
+            '\u{1F600}',
+        ] {
+            assert!(!tables::precis_freeform_prohibited(c), "{:?}", c);
+        }
+        for &c in &[
+            '\t', '\u{007F}', '\u{00AD}', '\u{0640}', '\u{1100}', '\u{2028}', '\u{E000}',
+            '\u{FFFF}', '\u{0378}',
+        ] {
+            assert!(tables::precis_freeform_prohibited(c), "{:?}", c);
+        }
+
+        assert!(tables::check_contextual_rules("l\u{00B7}l").is_ok());
+        assert!(tables::check_contextual_rules("a\u{00B7}l").is_err());
+        assert!(tables::check_contextual_rules("\u{0915}\u{094D}\u{200D}").is_ok());
+        assert!(tables::check_contextual_rules("\u{0915}\u{200D}").is_err());
+        assert!(tables::check_contextual_rules("\u{0375}\u{03B1}").is_ok());
+        assert!(tables::check_contextual_rules("\u{0375}a").is_err());
+        assert!(tables::check_contextual_rules("\u{05D0}\u{05F3}").is_ok());
+        assert!(tables::check_contextual_rules("\u{05F3}").is_err());
+        assert!(tables::check_contextual_rules("\u{0660}\u{0661}").is_ok());
+        assert!(tables::check_contextual_rules("\u{0660}\u{06F1}").is_err());
+    }
+
+    #[test]
+    fn saslprep_stored_and_query() {
+        assert_unassigned_code_point(saslprep_stored("\u{0221}"));
+        assert_eq!(saslprep_query("\u{0221}").unwrap(), "\u{0221}");
+        assert_eq!(saslprep_query("I\u{00AD}X\u{0221}").unwrap(), "IX\u{0221}");
+        assert_eq!(saslprep_query("user").unwrap(), "user");
+        assert_prohibited_character(saslprep_query("a\u{0007}\u{0221}"));
+
+        #[allow(deprecated)]
+        let prepared = saslprep("\u{0221}");
+        assert_unassigned_code_point(prepared);
+    }
+
+    #[test]
+    fn unicode_versions() {
+        assert!(tables::UNICODE_BIDI_VERSION >= tables::rfc3454_unicode_version());
+        assert_eq!(
+            tables::bidi_table_unicode_version(),
+            tables::UNICODE_BIDI_VERSION
+        );
+        assert!(tables::UNICODE_NORMALIZATION_VERSION >= tables::rfc3454_unicode_version());
+    }
+
+    #[test]
+    fn b2_first_mapped() {
+        assert_eq!(tables::B2_FIRST_MAPPED, 'A');
+        assert!(rfc3454::B_2.iter().all(|e| e.0 >= tables::B2_FIRST_MAPPED));
+        assert!(rfc3454::B_2
+            .iter()
+            .all(|e| !e.0.is_ascii() || e.0.is_ascii_uppercase()));
+        assert_eq!(tables::b2_lookup_binary('@'), None);
+        assert_eq!(tables::b2_lookup_binary('a'), None);
+        assert_eq!(tables::b2_lookup_binary('A'), Some("a"));
+        assert_eq!(tables::b2_lookup_binary('\u{00B5}'), Some("\u{03BC}"));
+    }
+
+    #[test]
+    fn table_sizes() {
+        assert_eq!(tables::A1_CHAR_RANGES, 396);
+        assert_eq!(tables::B2_MAPPINGS, 1371);
+    }
+
+    #[test]
+    fn all_saslprep_ascii() {
+        assert!(tables::is_all_saslprep_ascii(""));
+        assert!(tables::is_all_saslprep_ascii("a fairly long password~"));
+        for c in (0..=0xFF).filter_map(std::char::from_u32) {
+            for pos in 0..10 {
+                let mut s = "abcdefghij".to_string();
+                s.replace_range(pos..pos + 1, c.encode_utf8(&mut [0; 4]));
+                assert_eq!(
+                    tables::is_all_saslprep_ascii(&s),
+                    tables::all_printable_ascii(&s),
+                    "{:?}",
+                    s
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn unassigned_range() {
+        assert_eq!(
+            tables::unassigned_range('\u{0221}'),
+            Some(('\u{0221}', '\u{0221}'))
+        );
+        assert_eq!(
+            tables::unassigned_range('\u{0240}'),
+            Some(('\u{0234}', '\u{024F}'))
+        );
+        assert_eq!(tables::unassigned_range('a'), None);
+    }
+
+    #[test]
+    fn nfkc_quick_check() {
+        use tables::NormalizationStatus;
+
+        assert_eq!(tables::nfkc_quick_check("foo"), NormalizationStatus::Yes);
+        assert_eq!(
+            tables::nfkc_quick_check("\u{2168}"),
+            NormalizationStatus::No
+        );
+        assert_eq!(
+            tables::nfkc_quick_check("e\u{0301}"),
+            NormalizationStatus::Maybe
+        );
+    }
+
+    #[test]
+    fn compat_decomposable() {
+        assert!(tables::is_compat_decomposable('\u{2168}'));
+        assert!(tables::is_compat_decomposable('\u{00E9}'));
+        assert!(!tables::is_compat_decomposable('e'));
+        assert!(tables::has_any_compat_decomposable("a\u{FB01}"));
+        assert!(!tables::has_any_compat_decomposable("e\u{0301}"));
+    }
+
+    #[test]
+    fn hangul() {
+        assert!(tables::is_hangul_syllable('\u{D55C}'));
+        assert!(!tables::is_hangul_syllable('\u{1112}'));
+        assert!(tables::is_hangul_jamo('\u{1112}'));
+        assert!(tables::is_hangul_jamo('\u{11AB}'));
+        assert!(!tables::is_hangul_jamo('\u{314E}'));
+        assert!(tables::is_hangul_compatibility_jamo('\u{314E}'));
+        assert!(!tables::is_hangul_compatibility_jamo('\u{D55C}'));
+
+        let jamo = "\u{1112}\u{1161}\u{11AB}";
+        assert_eq!(jamo.nfkc().collect::<String>(), "\u{D55C}");
+        assert_eq!("\u{314E}".nfkc().collect::<String>(), "\u{1112}");
+    }
+
+    #[test]
+    fn nfkd_stable() {
+        assert!(tables::is_nfkd_stable("foo"));
+        assert!(tables::is_nfkd_stable("e\u{0301}"));
+        assert!(!tables::is_nfkd_stable("\u{00E9}"));
+        assert!(!tables::is_nfkd_stable("\u{2168}"));
+    }
+
+    #[test]
+    fn bidi_counts() {
+        let s = "ab\u{05D0}1\u{0627}";
+        assert_eq!(tables::count_r_or_al_chars(s), 2);
+        assert_eq!(tables::count_l_chars(s), 2);
+    }
+
+    #[test]
+    fn bidi_numbers() {
+        assert!(tables::is_european_number('1'));
+        assert!(!tables::is_european_number('\u{0661}'));
+        assert!(tables::is_arabic_number('\u{0661}'));
+        assert!(!tables::is_arabic_number('1'));
+        assert!(tables::has_both_en_and_an("\u{0627}1\u{0661}"));
+        assert!(!tables::has_both_en_and_an("\u{0627}12"));
+        assert!(!tables::has_both_en_and_an("\u{0627}\u{0661}"));
+        assert!(tables::is_bidi_separator('$'));
+        assert!(tables::is_bidi_separator(','));
+        assert!(tables::is_bidi_separator('+'));
+        assert!(!tables::is_bidi_separator('1'));
+        assert!(tables::is_bidi_on('!'));
+        assert!(!tables::is_bidi_on('a'));
+        assert!(tables::is_bidi_bn('\u{200B}'));
+        assert!(!tables::is_bidi_bn(' '));
+        for &c in &['+', '$', '\u{0661}', ',', '\u{0300}', '\u{200B}'] {
+            assert!(tables::is_bidi_weak_type(c));
+        }
+        assert!(!tables::is_bidi_weak_type('1'));
+        assert!(!tables::is_bidi_weak_type('!'));
+    }
+
+    #[test]
+    fn nsm_after_last_strong() {
+        assert!(tables::has_nsm_after_last_strong("\u{05D0}\u{05B0}"));
+        assert!(tables::has_nsm_after_last_strong("\u{05D0}1\u{0300}"));
+        assert!(!tables::has_nsm_after_last_strong(
+            "\u{05D0}\u{05B0}\u{05D1}"
+        ));
+        assert!(!tables::has_nsm_after_last_strong("a\u{0300}"));
+        assert!(tables::is_bidi_nsm('\u{05B0}'));
+        assert!(tables::is_bidi_nsm('\u{0300}'));
+        assert!(!tables::is_bidi_nsm('\u{05D0}'));
+    }
+
+    #[test]
+    fn canonical_combining_class() {
+        assert_eq!(tables::canonical_combining_class('a'), 0);
+        assert_eq!(tables::canonical_combining_class('\u{094D}'), 9);
+        assert_eq!(tables::canonical_combining_class('\u{0301}'), 230);
+    }
+
+    #[test]
+    fn check_not_all_spaces() {
+        assert!(tables::check_not_all_spaces("").is_ok());
+        assert!(tables::check_not_all_spaces(" a ").is_ok());
+        match tables::check_not_all_spaces(" \u{3000}") {
+            Err(Error(ErrorKind::AllSpaces, _)) => (),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn combining_mark_type() {
+        use tables::CombiningMarkType;
+
+        assert_eq!(
+            tables::combining_mark_type('\u{0301}'),
+            Some(CombiningMarkType::NonSpacing)
+        );
+        assert_eq!(
+            tables::combining_mark_type('\u{0903}'),
+            Some(CombiningMarkType::SpacingCombining)
+        );
+        assert_eq!(
+            tables::combining_mark_type('\u{20DD}'),
+            Some(CombiningMarkType::Enclosing)
+        );
+        assert_eq!(tables::combining_mark_type('a'), None);
+    }
+
+    #[test]
+    fn emoji() {
+        assert!(tables::is_emoji('\u{1F980}'));
+        assert!(tables::is_emoji_presentation('\u{1F980}'));
+        assert!(tables::is_emoji('\u{2764}'));
+        assert!(!tables::is_emoji_presentation('\u{2764}'));
+        assert!(!tables::is_emoji('a'));
+    }
+
+    #[test]
+    fn idna_mapping() {
+        assert_eq!(tables::idna_map_char('A'), Some('a'));
+        assert_eq!(tables::idna_map_char('\u{FF21}'), Some('a'));
+        assert_eq!(tables::idna_map_char('\u{FF76}'), Some('\u{30AB}'));
+        assert_eq!(tables::idna_map_char('a'), None);
+        assert_eq!(tables::idna_map_string("\u{FF25}xample.COM"), "example.com");
+        assert_eq!(tables::idna_map_string("\u{0130}"), "i\u{0307}");
+        assert_eq!(tables::idna_map_string("e\u{0301}"), "\u{00E9}");
+        if let Cow::Owned(_) = tables::idna_map_string("example.com") {
+            panic!("“example.com” should not be reallocated");
+        }
+    }
+
+    #[test]
+    fn first_non_ascii_position() {
+        assert_eq!(tables::first_non_ascii_position(""), None);
+        assert_eq!(tables::first_non_ascii_position("abcdefghijk"), None);
+        assert_eq!(tables::first_non_ascii_position("\u{00E9}"), Some(0));
+        assert_eq!(tables::first_non_ascii_position("abc\u{00E9}"), Some(3));
+        assert_eq!(
+            tables::first_non_ascii_position("abc\u{00E9}defgh"),
+            Some(3)
+        );
+        assert_eq!(
+            tables::first_non_ascii_position("abcdefgh\u{00E9}"),
+            Some(8)
+        );
+        assert_eq!(
+            tables::first_non_ascii_position("abcdefghijklm\u{00E9}"),
+            Some(13)
+        );
+        assert_eq!(
+            saslprep_stored("abcdefghij\u{00A0}").unwrap(),
+            "abcdefghij "
         );
-        assert_eq!(x520prep("UPPERCASED", true).unwrap(), "uppercased");
-        assert_starts_with_combining_char(x520prep("\u{0306}hello", true));
     }
 
     #[test]
@@ -1,13 +1,89 @@
 //! Character Tables
-use std::cmp::Ordering;
-use std::str::Chars;
-use unicode_bidi::{bidi_class, BidiClass};
-use unicode_properties::{GeneralCategoryGroup, UnicodeGeneralCategory};
+use alloc::borrow::Cow;
+use alloc::string::String;
+use alloc::vec::Vec;
+use core::cmp::Ordering;
+use core::iter;
+use core::ops::RangeInclusive;
+use core::str::Chars;
+use unicode_bidi::{self, bidi_class, BidiClass};
+use unicode_normalization::char::{self as normalization_char, decompose_compatible};
+use unicode_normalization::{self, is_nfkc_quick, is_nfkd, IsNormalized, UnicodeNormalization};
+use unicode_properties::emoji::EmojiStatus;
+use unicode_properties::{
+    GeneralCategory, GeneralCategoryGroup, UnicodeEmoji, UnicodeGeneralCategory,
+};
 
 use super::rfc3454;
+use super::{Error, ErrorKind};
+
+/// The number of code point ranges in table A.1.
+pub const A1_CHAR_RANGES: usize = rfc3454::A_1.len();
+
+/// The number of mappings in table B.2.
+pub const B2_MAPPINGS: usize = rfc3454::B_2.len();
+
+/// The lowest code point with a mapping in table B.2, U+0041 LATIN CAPITAL
+/// LETTER A.
+pub const B2_FIRST_MAPPED: char = rfc3454::B_2[0].0;
+
+/// A version of Unicode, as its major, minor and update version numbers.
+pub type UnicodeVersion = (u8, u8, u8);
+
+/// The version of Unicode used by the `unicode-bidi` crate, which provides the
+/// bidirectional properties used for tables D.1 and D.2.
+///
+/// This is generally much newer than `rfc3454_unicode_version`.
+pub const UNICODE_BIDI_VERSION: UnicodeVersion = (
+    unicode_bidi::UNICODE_VERSION.0 as u8,
+    unicode_bidi::UNICODE_VERSION.1 as u8,
+    unicode_bidi::UNICODE_VERSION.2 as u8,
+);
+
+// Fails to compile if a component of the version would be truncated by the
+// conversion above, as the index is then out of bounds.
+const _: () = [(); 1][(unicode_bidi::UNICODE_VERSION.0 > 0xFF
+    || unicode_bidi::UNICODE_VERSION.1 > 0xFF
+    || unicode_bidi::UNICODE_VERSION.2 > 0xFF) as usize];
+
+/// The version of Unicode used by the `unicode-normalization` crate, which
+/// provides NFKC normalization.
+///
+/// RFC 3454 requires normalization as of Unicode 3.2, but Unicode's stability
+/// policy means normalization only differs from that for code points which
+/// were unassigned in 3.2, and which stored strings may not contain.
+pub const UNICODE_NORMALIZATION_VERSION: UnicodeVersion = (
+    unicode_normalization::UNICODE_VERSION.0,
+    unicode_normalization::UNICODE_VERSION.1,
+    unicode_normalization::UNICODE_VERSION.2,
+);
+
+/// Returns the version of Unicode the tables of RFC 3454 are derived from.
+#[must_use]
+pub fn rfc3454_unicode_version() -> UnicodeVersion {
+    (3, 2, 0)
+}
+
+/// Returns the version of Unicode used by the bidirectional property lookups
+/// in tables D.1 and D.2.
+///
+/// This is `UNICODE_BIDI_VERSION`, the version of the `unicode-bidi` crate's
+/// data, which is generally much newer than `rfc3454_unicode_version`.
+#[must_use]
+pub fn bidi_table_unicode_version() -> UnicodeVersion {
+    UNICODE_BIDI_VERSION
+}
 
 /// A.1 Unassigned code points in Unicode 3.2
+#[must_use = "the prohibition check result must be used"]
 pub fn unassigned_code_point(c: char) -> bool {
+    unassigned_range(c).is_some()
+}
+
+/// Returns the inclusive range of table A.1 containing `c`, or `None` if `c`
+/// is assigned in Unicode 3.2.
+#[must_use]
+pub fn unassigned_range(c: char) -> Option<(char, char)> {
     rfc3454::A_1
         .binary_search_by(|&(start, end)| {
             if start > c {
@@ -18,10 +94,31 @@ pub fn unassigned_code_point(c: char) -> bool {
                 Ordering::Equal
             }
         })
-        .is_ok()
+        .ok()
+        .map(|idx| rfc3454::A_1[idx])
+}
+
+/// Determines if every code point from `start` to `end` inclusive is in table
+/// A.1, i.e. unassigned in Unicode 3.2.
+///
+/// This searches the ranges of table A.1 rather than checking each code point.
+/// An empty range, where `start > end`, is trivially unassigned.
+#[must_use = "the prohibition check result must be used"]
+pub fn unassigned_range_check(start: char, end: char) -> bool {
+    if start > end {
+        return true;
+    }
+
+    // the ranges of table A.1 are never adjacent, so a range of unassigned
+    // code points is always contained in a single one of them
+    match unassigned_range(start) {
+        Some((_, hi)) => end <= hi,
+        None => false,
+    }
 }
 
 /// B.1 Commonly mapped to nothing
+#[must_use = "the prohibition check result must be used"]
 pub fn commonly_mapped_to_nothing(c: char) -> bool {
     matches!(
         c,
@@ -55,21 +152,139 @@ pub fn commonly_mapped_to_nothing(c: char) -> bool {
     )
 }
 
+/// Returns an iterator over the characters of table B.1, in ascending order.
+pub fn commonly_mapped_to_nothing_chars() -> impl Iterator<Item = char> {
+    let variation_selectors = '\u{FE00}'..='\u{FE0F}';
+    [
+        '\u{00AD}', '\u{034F}', '\u{1806}', '\u{180B}', '\u{180C}', '\u{180D}', '\u{200B}',
+        '\u{200C}', '\u{200D}', '\u{2060}',
+    ]
+    .iter()
+    .cloned()
+    .chain(variation_selectors)
+    .chain(iter::once('\u{FEFF}'))
+}
+
+/// Determines if `c` is in table B.1 and also in one of the C tables.
+///
+/// These are U+200B ZERO WIDTH SPACE, which is in C.1.2, and U+200C ZERO WIDTH
+/// NON-JOINER, U+200D ZERO WIDTH JOINER, U+2060 WORD JOINER and U+FEFF ZERO
+/// WIDTH NO-BREAK SPACE, which are in C.2.2. Since mapping happens before the
+/// prohibited output check, profiles which map B.1 to nothing silently remove
+/// these characters rather than rejecting the string. SASLprep is an exception
+/// for U+200B, which it maps to a space along with the rest of C.1.2.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_in_b1_and_c_table(c: char) -> bool {
+    commonly_mapped_to_nothing(c)
+        && (non_ascii_space_character(c)
+            || non_ascii_control_character(c)
+            || private_use(c)
+            || non_character_code_point(c)
+            || surrogate_code(c)
+            || inappropriate_for_plain_text(c)
+            || inappropriate_for_canonical_representation(c)
+            || change_display_properties_or_deprecated(c)
+            || tagging_character(c))
+}
+
+/// U+00AD SOFT HYPHEN.
+pub const SOFT_HYPHEN: char = '\u{00AD}';
+
+/// Determines if `c` is U+00AD SOFT HYPHEN.
+///
+/// A soft hyphen marks a position where a word may be hyphenated if it is
+/// broken across lines, and is otherwise invisible; HTML's `&shy;` entity is
+/// the usual way of inserting one. Since text copied from a rendered document
+/// may or may not contain soft hyphens, it is in table B.1 and removed by the
+/// mapping step of every profile which uses that table, and X.520 string
+/// preparation removes it likewise. It is not prohibited by any of the C
+/// tables, so a custom profile which doesn't map B.1 to nothing leaves it in
+/// place.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_soft_hyphen(c: char) -> bool {
+    c == SOFT_HYPHEN
+}
+
+/// Determines if any character of `s` is a B.1 character.
+#[must_use = "the prohibition check result must be used"]
+pub fn has_commonly_mapped_to_nothing_char(s: &str) -> bool {
+    s.contains(commonly_mapped_to_nothing)
+}
+
+/// Removes all B.1 characters from `s`.
+///
+/// The string is only reallocated if it contains a B.1 character.
+#[must_use]
+pub fn remove_b1_chars(s: &str) -> Cow<'_, str> {
+    match s.find(commonly_mapped_to_nothing) {
+        Some(idx) => {
+            let mut out = String::with_capacity(s.len());
+            out.push_str(&s[..idx]);
+            out.extend(s[idx..].chars().filter(|&c| !commonly_mapped_to_nothing(c)));
+            Cow::Owned(out)
+        }
+        None => Cow::Borrowed(s),
+    }
+}
+
 /// B.2 Mapping for case-folding used with NFKC.
+#[must_use]
 pub fn case_fold_for_nfkc(c: char) -> CaseFoldForNfkc {
-    let inner = match rfc3454::B_2.binary_search_by_key(&c, |e| e.0) {
-        Ok(idx) => FoldInner::Chars(rfc3454::B_2[idx].1.chars()),
-        Err(_) => FoldInner::Char(Some(c)),
+    let inner = match b2_lookup_binary(c) {
+        Some(s) => FoldInner::Chars(s.chars()),
+        None => FoldInner::Char(Some(c)),
     };
     CaseFoldForNfkc(inner)
 }
 
+/// Determines if any character of `s` has a mapping in table B.2.
+#[must_use = "the prohibition check result must be used"]
+pub fn has_case_foldable_char(s: &str) -> bool {
+    s.chars().any(|c| b2_lookup_binary(c).is_some())
+}
+
+/// Determines if `s` contains an ASCII uppercase letter, all of which have a
+/// B.2 mapping.
+#[must_use = "the prohibition check result must be used"]
+pub fn has_ascii_uppercase(s: &str) -> bool {
+    s.bytes().any(|b| b.is_ascii_uppercase())
+}
+
+/// Applies the B.2 case folding mapping to every character of `s`.
+///
+/// The string is only reallocated if a character has a mapping. Unlike the
+/// profiles, no normalization is performed.
+#[must_use]
+pub fn apply_case_fold(s: &str) -> Cow<'_, str> {
+    if has_case_foldable_char(s) {
+        Cow::Owned(s.chars().flat_map(case_fold_for_nfkc).collect())
+    } else {
+        Cow::Borrowed(s)
+    }
+}
+
+/// Looks up the B.2 mapping of `c` with a binary search of the table.
+///
+/// Returns `None` if `c` maps to itself.
+#[must_use]
+pub fn b2_lookup_binary(c: char) -> Option<&'static str> {
+    // the only ASCII characters with mappings are the uppercase letters
+    if c < B2_FIRST_MAPPED || (c.is_ascii() && !c.is_ascii_uppercase()) {
+        return None;
+    }
+
+    rfc3454::B_2
+        .binary_search_by_key(&c, |e| e.0)
+        .ok()
+        .map(|idx| rfc3454::B_2[idx].1)
+}
+
 enum FoldInner {
     Chars(Chars<'static>),
     Char(Option<char>),
 }
 
-/// The iterator returned by `case_fold_for_nfkc`.
+/// The iterator returned by `case_fold_for_nfkc` and `map_chars_nameprep`.
 pub struct CaseFoldForNfkc(FoldInner);
 
 impl Iterator for CaseFoldForNfkc {
@@ -83,12 +298,46 @@ impl Iterator for CaseFoldForNfkc {
     }
 }
 
+/// Maps a character according to section 2.1 of [RFC 4013][].
+///
+/// Returns `Some(' ')` if the character is a non-ASCII space character, `None`
+/// if it is mapped to nothing, and `Some(c)` otherwise.
+///
+/// [RFC 4013]: https://tools.ietf.org/html/rfc4013
+#[must_use]
+pub fn map_char_saslprep(c: char) -> Option<char> {
+    if non_ascii_space_character(c) {
+        Some(' ')
+    } else if commonly_mapped_to_nothing(c) {
+        None
+    } else {
+        Some(c)
+    }
+}
+
+/// Maps a character according to section 3 of [RFC 3491][].
+///
+/// Characters mapped to nothing yield an empty iterator; all others are
+/// case-folded with table B.2.
+///
+/// [RFC 3491]: https://tools.ietf.org/html/rfc3491
+#[must_use]
+pub fn map_chars_nameprep(c: char) -> CaseFoldForNfkc {
+    if commonly_mapped_to_nothing(c) {
+        CaseFoldForNfkc(FoldInner::Char(None))
+    } else {
+        case_fold_for_nfkc(c)
+    }
+}
+
 /// C.1.1 ASCII space characters
+#[must_use = "the prohibition check result must be used"]
 pub fn ascii_space_character(c: char) -> bool {
     c == ' '
 }
 
 /// C.1.2 Non-ASCII space characters
+#[must_use = "the prohibition check result must be used"]
 pub fn non_ascii_space_character(c: char) -> bool {
     matches!(
         c,
@@ -112,12 +361,124 @@ pub fn non_ascii_space_character(c: char) -> bool {
     )
 }
 
+/// Determines if `c` has the Unicode general category Zs (Space Separator) in
+/// the current version of Unicode.
+///
+/// This differs from the fixed tables of RFC 3454: Zs includes U+0020, which
+/// is in C.1.1 rather than C.1.2, and excludes U+200B ZERO WIDTH SPACE, which
+/// is now a format character. The stringprep profiles use C.1.1 and C.1.2,
+/// while PRECIS ([RFC 8264][]) uses the Zs category of whichever Unicode
+/// version is in use.
+///
+/// [RFC 8264]: https://tools.ietf.org/html/rfc8264
+#[must_use = "the prohibition check result must be used"]
+pub fn is_unicode_zs_category(c: char) -> bool {
+    c.general_category() == GeneralCategory::SpaceSeparator
+}
+
+/// C.1.1 ASCII space characters and C.1.2 Non-ASCII space characters
+#[must_use = "the prohibition check result must be used"]
+pub fn is_white_space(c: char) -> bool {
+    ascii_space_character(c) || non_ascii_space_character(c)
+}
+
+/// Determines if `s` contains any C.1.1 or C.1.2 space character.
+#[must_use = "the prohibition check result must be used"]
+pub fn has_any_whitespace(s: &str) -> bool {
+    s.chars().any(is_white_space)
+}
+
+/// Returns the number of C.1.2 non-ASCII space characters in `s`.
+#[must_use]
+pub fn count_non_ascii_space_chars(s: &str) -> usize {
+    s.chars().filter(|&c| non_ascii_space_character(c)).count()
+}
+
+/// C.2.1 ASCII control characters U+0000 NULL to U+001F INFORMATION
+/// SEPARATOR ONE.
+pub const ASCII_CONTROL_RANGE: RangeInclusive<char> = '\u{0000}'..='\u{001F}';
+
+/// C.2.1 ASCII control character U+007F DELETE.
+pub const ASCII_DELETE: char = '\u{007F}';
+
 /// C.2.1 ASCII control characters
+///
+/// This is `ASCII_CONTROL_RANGE` and `ASCII_DELETE`.
+#[must_use = "the prohibition check result must be used"]
 pub fn ascii_control_character(c: char) -> bool {
-    matches!(c, '\u{0000}'..='\u{001F}' | '\u{007F}')
+    ASCII_CONTROL_RANGE.contains(&c) || c == ASCII_DELETE
+}
+
+/// Determines if `c` is an ASCII character that is not a C.2.1 control
+/// character.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_printable_ascii(c: char) -> bool {
+    c.is_ascii() && !ascii_control_character(c)
+}
+
+/// Determines if every character of `s` is printable ASCII.
+#[must_use = "the prohibition check result must be used"]
+pub fn all_printable_ascii(s: &str) -> bool {
+    s.bytes().all(|b| matches!(b, 0x20..=0x7E))
+}
+
+/// Returns the byte index of the first non-ASCII character of `s`, or `None`
+/// if `s` is entirely ASCII, checking eight bytes at a time.
+///
+/// Since stringprep maps ASCII characters to themselves, only the part of `s`
+/// from this index onward can contain characters needing to be mapped.
+#[must_use]
+pub fn first_non_ascii_position(s: &str) -> Option<usize> {
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    let bytes = s.as_bytes();
+    let mut chunks = bytes.chunks_exact(8);
+    let mut offset = 0;
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let x = u64::from_le_bytes(word) & HI;
+        if x != 0 {
+            return Some(offset + x.trailing_zeros() as usize / 8);
+        }
+        offset += 8;
+    }
+
+    chunks
+        .remainder()
+        .iter()
+        .position(|&b| b >= 0x80)
+        .map(|idx| offset + idx)
+}
+
+/// Determines if every character of `s` is printable ASCII, checking eight
+/// bytes at a time.
+///
+/// This is equivalent to `all_printable_ascii` but faster for long strings.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_all_saslprep_ascii(s: &str) -> bool {
+    const LO: u64 = 0x0101_0101_0101_0101;
+    const HI: u64 = 0x8080_8080_8080_8080;
+
+    let bytes = s.as_bytes();
+    let mut chunks = bytes.chunks_exact(8);
+    for chunk in &mut chunks {
+        let mut word = [0; 8];
+        word.copy_from_slice(chunk);
+        let x = u64::from_le_bytes(word);
+        // The high bit of a byte is set if it is non-ASCII, below 0x20, or at
+        // least 0x7F.
+        let invalid = x | (x.wrapping_sub(LO * 0x20) & !x) | x.wrapping_add(LO);
+        if invalid & HI != 0 {
+            return false;
+        }
+    }
+
+    chunks.remainder().iter().all(|&b| matches!(b, 0x20..=0x7E))
 }
 
 /// C.2.2 Non-ASCII control characters
+#[must_use = "the prohibition check result must be used"]
 pub fn non_ascii_control_character(c: char) -> bool {
     matches!(c, '\u{0080}'..='\u{009F}'
         | '\u{06DD}'
@@ -137,12 +498,43 @@ pub fn non_ascii_control_character(c: char) -> bool {
         | '\u{1D173}'..='\u{1D17A}')
 }
 
+/// Supplementary plane format characters added to Unicode after version 3.2.
+///
+/// This is a modern extension to table C.2.2, which only covers the musical
+/// symbol formatting characters U+1D173 to U+1D17A outside of the BMP. Tagging
+/// characters are covered by table C.9 and are not included.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_supplementary_formatting(c: char) -> bool {
+    matches!(c, '\u{110BD}'
+        | '\u{110CD}'
+        | '\u{13430}'..='\u{1343F}'
+        | '\u{1BCA0}'..='\u{1BCA3}')
+}
+
 /// C.3 Private use
+#[must_use = "the prohibition check result must be used"]
 pub fn private_use(c: char) -> bool {
-    matches!(c, '\u{E000}'..='\u{F8FF}' | '\u{F0000}'..='\u{FFFFD}' | '\u{100000}'..='\u{10FFFD}')
+    is_private_use_bmp(c) || is_private_use_supplementary(c)
+}
+
+/// C.3 Private use, restricted to the Basic Multilingual Plane
+#[must_use = "the prohibition check result must be used"]
+pub fn is_private_use_bmp(c: char) -> bool {
+    matches!(c, '\u{E000}'..='\u{F8FF}')
+}
+
+/// C.3 Private use, restricted to the supplementary private use planes
+#[must_use = "the prohibition check result must be used"]
+pub fn is_private_use_supplementary(c: char) -> bool {
+    matches!(c, '\u{F0000}'..='\u{FFFFD}' | '\u{100000}'..='\u{10FFFD}')
 }
 
 /// C.4 Non-character code points
+///
+/// These are the 66 code points permanently reserved for internal use: the 32
+/// code points U+FDD0 to U+FDEF, and the last two code points of each of the
+/// 17 planes, U+xFFFE and U+xFFFF.
+#[must_use = "the prohibition check result must be used"]
 pub fn non_character_code_point(c: char) -> bool {
     matches!(c, '\u{FDD0}'..='\u{FDEF}'
         | '\u{FFFE}'..='\u{FFFF}'
@@ -165,6 +557,11 @@ pub fn non_character_code_point(c: char) -> bool {
 }
 
 /// C.5 Surrogate codes
+///
+/// A `char` can never be a surrogate code point, so this always returns
+/// `false`. Use `is_utf16_surrogate_in_bytes` or `is_wtf8_surrogate` to check
+/// encoded data received from systems which permit surrogates.
+#[must_use = "the prohibition check result must be used"]
 #[allow(clippy::match_single_binding)]
 pub fn surrogate_code(c: char) -> bool {
     match c {
@@ -174,7 +571,54 @@ pub fn surrogate_code(c: char) -> bool {
     }
 }
 
+/// C.5 Surrogate codes, checked in CESU-8 encoded bytes
+///
+/// Returns `true` if `b` contains a surrogate code point which is not part of
+/// a high-low surrogate pair.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_utf16_surrogate_in_bytes(b: &[u8]) -> bool {
+    let mut i = 0;
+    while i < b.len() {
+        match encoded_surrogate(&b[i..]) {
+            Some(0xD800..=0xDBFF) => match encoded_surrogate(&b[i + 3..]) {
+                Some(0xDC00..=0xDFFF) => i += 6,
+                _ => return true,
+            },
+            Some(_) => return true,
+            None => i += 1,
+        }
+    }
+    false
+}
+
+/// C.5 Surrogate codes, checked in WTF-8 encoded bytes
+///
+/// WTF-8 encodes surrogate pairs as a single supplementary code point, so any
+/// encoded surrogate in `b` is unpaired.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_wtf8_surrogate(b: &[u8]) -> bool {
+    (0..b.len()).any(|i| encoded_surrogate(&b[i..]).is_some())
+}
+
+// Decodes a three byte generalized UTF-8 sequence for a surrogate code point at
+// the start of `b`.
+fn encoded_surrogate(b: &[u8]) -> Option<u32> {
+    match *b {
+        [0xED, b1 @ 0xA0..=0xBF, b2 @ 0x80..=0xBF, ..] => {
+            Some(0xD000 | (u32::from(b1 & 0x3F) << 6) | u32::from(b2 & 0x3F))
+        }
+        _ => None,
+    }
+}
+
 /// C.6 Inappropriate for plain text
+///
+/// These are the interlinear annotation characters U+FFF9 to U+FFFB, U+FFFC
+/// OBJECT REPLACEMENT CHARACTER and U+FFFD REPLACEMENT CHARACTER. The latter
+/// stands in for data which could not be decoded, so a string containing it
+/// has already lost information and cannot be reliably compared. U+FFFE and
+/// U+FFFF are non-character code points, covered by C.4.
+#[must_use = "the prohibition check result must be used"]
 pub fn inappropriate_for_plain_text(c: char) -> bool {
     matches!(
         c,
@@ -183,49 +627,859 @@ pub fn inappropriate_for_plain_text(c: char) -> bool {
 }
 
 /// C.7 Inappropriate for canonical representation
+///
+/// These are the ideographic description characters U+2FF0 to U+2FFB, which
+/// describe the structure of a CJK ideograph as an Ideographic Description
+/// Sequence rather than encoding it. Such a sequence is not equivalent to the
+/// ideograph it describes, so it cannot be a canonical representation of it.
+/// The ideographic description characters added after Unicode 3.2 (U+2FFC to
+/// U+2FFF and U+31EF) are not included.
+#[must_use = "the prohibition check result must be used"]
 pub fn inappropriate_for_canonical_representation(c: char) -> bool {
     matches!(c, '\u{2FF0}'..='\u{2FFB}')
 }
 
+/// The code points of table C.8, in ascending order.
+pub const C8_CODE_POINTS: [char; 15] = [
+    '\u{0340}', '\u{0341}', '\u{200E}', '\u{200F}', '\u{202A}', '\u{202B}', '\u{202C}', '\u{202D}',
+    '\u{202E}', '\u{206A}', '\u{206B}', '\u{206C}', '\u{206D}', '\u{206E}', '\u{206F}',
+];
+
+/// Determines if `c` is in the Arabic Presentation Forms-A or Arabic
+/// Presentation Forms-B blocks, U+FB50 to U+FDFF and U+FE70 to U+FEFF.
+///
+/// These contextual forms and ligatures are not in table C.7, since their
+/// compatibility decompositions to the nominal Arabic letters mean NFKC
+/// normalization replaces them rather than the string being rejected. The
+/// blocks also contain the non-characters U+FDD0 to U+FDEF, which are
+/// prohibited by C.4, and U+FEFF ZERO WIDTH NO-BREAK SPACE, which is in B.1.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_arabic_presentation_form(c: char) -> bool {
+    matches!(c, '\u{FB50}'..='\u{FDFF}' | '\u{FE70}'..='\u{FEFF}')
+}
+
 /// C.8 Change display properties or are deprecated
+#[must_use = "the prohibition check result must be used"]
 pub fn change_display_properties_or_deprecated(c: char) -> bool {
-    matches!(
-        c,
-        '\u{0340}'
-            | '\u{0341}'
-            | '\u{200E}'
-            | '\u{200F}'
-            | '\u{202A}'
-            | '\u{202B}'
-            | '\u{202C}'
-            | '\u{202D}'
-            | '\u{202E}'
-            | '\u{206A}'
-            | '\u{206B}'
-            | '\u{206C}'
-            | '\u{206D}'
-            | '\u{206E}'
-            | '\u{206F}'
-    )
+    C8_CODE_POINTS.contains(&c)
+}
+
+/// C.8 Change display properties
+///
+/// These are the bidirectional formatting characters U+200E LEFT-TO-RIGHT MARK,
+/// U+200F RIGHT-TO-LEFT MARK, and U+202A to U+202E, the embedding and override
+/// controls.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_change_display_properties(c: char) -> bool {
+    matches!(c, '\u{200E}' | '\u{200F}' | '\u{202A}'..='\u{202E}')
+}
+
+/// C.8 Deprecated
+///
+/// These are U+0340 COMBINING GRAVE TONE MARK and U+0341 COMBINING ACUTE TONE
+/// MARK, which are canonically equivalent to U+0300 and U+0301, and the
+/// deprecated format characters U+206A to U+206F, which control symmetric
+/// swapping, Arabic form shaping and digit shapes.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_deprecated_format(c: char) -> bool {
+    matches!(c, '\u{0340}' | '\u{0341}' | '\u{206A}'..='\u{206F}')
 }
 
 /// C.9 Tagging characters
+///
+/// These are U+E0001 LANGUAGE TAG and the tag characters U+E0020 to U+E007F,
+/// which were intended to mark the language of the text that follows them, by
+/// spelling out a language tag with invisible copies of the ASCII characters.
+/// That use is deprecated in favour of markup, and since the tags are
+/// invisible, two strings which differ only by them would look the same.
+///
+/// Note that while they lie near the end of the code space, these characters
+/// are in the Supplementary Special-purpose Plane (plane 14), not the private
+/// use planes 15 and 16, so they are not covered by C.3.
+#[must_use = "the prohibition check result must be used"]
 pub fn tagging_character(c: char) -> bool {
     matches!(c, '\u{E0001}' | '\u{E0020}'..='\u{E007F}')
 }
 
+/// Determines if `c` is an invisible formatting character, i.e. is in table
+/// B.1, C.2.2, C.8 or C.9.
+///
+/// This covers zero width spaces and joiners, variation selectors,
+/// bidirectional controls and tag characters, and is intended for code which
+/// wants to strip invisible formatting from user input.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_invisible_format_char(c: char) -> bool {
+    commonly_mapped_to_nothing(c)
+        || non_ascii_control_character(c)
+        || change_display_properties_or_deprecated(c)
+        || tagging_character(c)
+}
+
+/// Determines if `c` is one of the ASCII characters `"&'/:<>@`, which
+/// Nodeprep prohibits in addition to the C tables, as described in
+/// [RFC 6122, Appendix A.5][].
+///
+/// [RFC 6122, Appendix A.5]: https://tools.ietf.org/html/rfc6122#appendix-A.5
+#[must_use = "the prohibition check result must be used"]
+pub fn nodeprep_prohibited_ascii(c: char) -> bool {
+    matches!(c, '"' | '&' | '\'' | '/' | ':' | '<' | '>' | '@')
+}
+
+/// Returns the identifier of the first table of RFC 3454 prohibiting `c` in
+/// SASLprep, e.g. `"C.2.1"`, or `None` if `c` is allowed.
+///
+/// The C tables are checked in order, followed by table A.1 of unassigned
+/// code points, which only stored strings must not contain.
+#[must_use]
+pub fn prohibited_by(c: char) -> Option<&'static str> {
+    if non_ascii_space_character(c) {
+        Some("C.1.2")
+    } else if ascii_control_character(c) {
+        Some("C.2.1")
+    } else if non_ascii_control_character(c) {
+        Some("C.2.2")
+    } else if private_use(c) {
+        Some("C.3")
+    } else if non_character_code_point(c) {
+        Some("C.4")
+    } else if surrogate_code(c) {
+        Some("C.5")
+    } else if inappropriate_for_plain_text(c) {
+        Some("C.6")
+    } else if inappropriate_for_canonical_representation(c) {
+        Some("C.7")
+    } else if change_display_properties_or_deprecated(c) {
+        Some("C.8")
+    } else if tagging_character(c) {
+        Some("C.9")
+    } else if unassigned_code_point(c) {
+        Some("A.1")
+    } else {
+        None
+    }
+}
+
 /// D.1 Characters with bidirectional property "R" or "AL"
+#[must_use = "the prohibition check result must be used"]
 pub fn bidi_r_or_al(c: char) -> bool {
     matches!(bidi_class(c), BidiClass::R | BidiClass::AL)
 }
 
 /// D.2 Characters with bidirectional property "L"
+#[must_use = "the prohibition check result must be used"]
 pub fn bidi_l(c: char) -> bool {
     matches!(bidi_class(c), BidiClass::L)
 }
 
+/// Returns the number of D.1 RandALCat characters in `s`.
+#[must_use]
+pub fn count_r_or_al_chars(s: &str) -> usize {
+    s.chars().filter(|&c| bidi_r_or_al(c)).count()
+}
+
+/// Returns the number of D.2 LCat characters in `s`.
+#[must_use]
+pub fn count_l_chars(s: &str) -> usize {
+    s.chars().filter(|&c| bidi_l(c)).count()
+}
+
+/// Determines if `s` contains a character with bidirectional property "NSM"
+/// after its last character with bidirectional property "R", "AL", "EN" or
+/// "AN", as described in [RFC 5893, Section 2][].
+///
+/// [RFC 5893, Section 2]: https://tools.ietf.org/html/rfc5893#section-2
+#[must_use = "the prohibition check result must be used"]
+pub fn has_nsm_after_last_strong(s: &str) -> bool {
+    let mut nsm = false;
+    for c in s.chars().rev() {
+        match bidi_class(c) {
+            BidiClass::R | BidiClass::AL | BidiClass::EN | BidiClass::AN => return nsm,
+            BidiClass::NSM => nsm = true,
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Determines if `c` has bidirectional property "EN" (European Number).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_european_number(c: char) -> bool {
+    bidi_class(c) == BidiClass::EN
+}
+
+/// Determines if `c` has bidirectional property "AN" (Arabic Number).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_arabic_number(c: char) -> bool {
+    bidi_class(c) == BidiClass::AN
+}
+
+/// Determines if `c` has bidirectional property "NSM" (Non-Spacing Mark).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_bidi_nsm(c: char) -> bool {
+    bidi_class(c) == BidiClass::NSM
+}
+
+/// Determines if `c` has bidirectional property "ET", "CS" or "ES" (European
+/// Number Terminator, Common Number Separator or European Number Separator).
+///
+/// These are among the classes allowed in right-to-left labels by rule 2 of
+/// [RFC 5893, Section 2][].
+///
+/// [RFC 5893, Section 2]: https://tools.ietf.org/html/rfc5893#section-2
+#[must_use = "the prohibition check result must be used"]
+pub fn is_bidi_separator(c: char) -> bool {
+    matches!(bidi_class(c), BidiClass::ET | BidiClass::CS | BidiClass::ES)
+}
+
+/// Determines if `c` has bidirectional property "ON" (Other Neutral).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_bidi_on(c: char) -> bool {
+    bidi_class(c) == BidiClass::ON
+}
+
+/// Determines if `c` has bidirectional property "BN" (Boundary Neutral).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_bidi_bn(c: char) -> bool {
+    bidi_class(c) == BidiClass::BN
+}
+
+/// Determines if `c` has one of the weak bidirectional properties other than
+/// "EN", i.e. "ES", "ET", "AN", "CS", "NSM" or "BN".
+///
+/// Together with "R", "AL", "EN" and "ON", these are the classes allowed in
+/// right-to-left labels by rule 2 of [RFC 5893, Section 2][].
+///
+/// [RFC 5893, Section 2]: https://tools.ietf.org/html/rfc5893#section-2
+#[must_use = "the prohibition check result must be used"]
+pub fn is_bidi_weak_type(c: char) -> bool {
+    matches!(
+        bidi_class(c),
+        BidiClass::ES
+            | BidiClass::ET
+            | BidiClass::AN
+            | BidiClass::CS
+            | BidiClass::NSM
+            | BidiClass::BN
+    )
+}
+
+/// Determines if `s` contains both a character with bidirectional property
+/// "EN" and one with bidirectional property "AN", which rule 4 of
+/// [RFC 5893, Section 2][] prohibits in right-to-left labels.
+///
+/// [RFC 5893, Section 2]: https://tools.ietf.org/html/rfc5893#section-2
+#[must_use = "the prohibition check result must be used"]
+pub fn has_both_en_and_an(s: &str) -> bool {
+    let mut en = false;
+    let mut an = false;
+    for c in s.chars() {
+        match bidi_class(c) {
+            BidiClass::EN => en = true,
+            BidiClass::AN => an = true,
+            _ => continue,
+        }
+        if en && an {
+            return true;
+        }
+    }
+    false
+}
+
+/// Determines if `c` is a combining character, i.e. has the Unicode general
+/// category Mark (Mn, Mc or Me).
+#[must_use = "the prohibition check result must be used"]
+pub fn is_combining_character(c: char) -> bool {
+    c.general_category_group() == GeneralCategoryGroup::Mark
+}
+
+/// The kind of a combining mark, i.e. its Unicode general category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CombiningMarkType {
+    /// A non-spacing mark (Mn).
+    NonSpacing,
+    /// A spacing combining mark (Mc).
+    SpacingCombining,
+    /// An enclosing mark (Me).
+    Enclosing,
+}
+
+/// Returns the kind of combining mark `c` is, or `None` if it is not a
+/// combining character.
+#[must_use]
+pub fn combining_mark_type(c: char) -> Option<CombiningMarkType> {
+    match c.general_category() {
+        GeneralCategory::NonspacingMark => Some(CombiningMarkType::NonSpacing),
+        GeneralCategory::SpacingMark => Some(CombiningMarkType::SpacingCombining),
+        GeneralCategory::EnclosingMark => Some(CombiningMarkType::Enclosing),
+        _ => None,
+    }
+}
+
+/// Returns the Unicode Canonical_Combining_Class of `c`.
+///
+/// This is 0 for most characters, and e.g. 9 for viramas.
+#[must_use]
+pub fn canonical_combining_class(c: char) -> u8 {
+    normalization_char::canonical_combining_class(c)
+}
+
+/// Determines if `c` has the Unicode `Emoji` property.
+///
+/// Note that this includes the ASCII digits, `#` and `*`. Most emoji were
+/// unassigned in Unicode 3.2 and are therefore rejected by the stringprep
+/// profiles as unassigned code points, while the PRECIS FreeformClass permits
+/// them as symbols.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_emoji(c: char) -> bool {
+    c.is_emoji_char()
+}
+
+/// Determines if `c` has the Unicode `Emoji_Presentation` property, i.e. is
+/// displayed as an emoji rather than as text by default.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_emoji_presentation(c: char) -> bool {
+    matches!(
+        c.emoji_status(),
+        EmojiStatus::EmojiPresentation
+            | EmojiStatus::EmojiPresentationAndModifierBase
+            | EmojiStatus::EmojiPresentationAndEmojiComponent
+            | EmojiStatus::EmojiPresentationAndModifierAndEmojiComponent
+    )
+}
+
+/// The result of a normalization quick check.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NormalizationStatus {
+    /// The string is normalized.
+    Yes,
+    /// The string is not normalized.
+    No,
+    /// The string may or may not be normalized.
+    Maybe,
+}
+
+/// Checks if `s` is in NFKC form using the NFKC_Quick_Check property.
+///
+/// A result of `Maybe` requires a full normalization to resolve.
+#[must_use]
+pub fn nfkc_quick_check(s: &str) -> NormalizationStatus {
+    match is_nfkc_quick(s.chars()) {
+        IsNormalized::Yes => NormalizationStatus::Yes,
+        IsNormalized::No => NormalizationStatus::No,
+        IsNormalized::Maybe => NormalizationStatus::Maybe,
+    }
+}
+
+/// Determines if `c` is changed by compatibility decomposition.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_compat_decomposable(c: char) -> bool {
+    let mut unchanged = true;
+    decompose_compatible(c, |d| unchanged &= d == c);
+    !unchanged
+}
+
+/// Determines if any character of `s` is changed by compatibility
+/// decomposition.
+///
+/// Note that NFKC may still change a string for which this returns `false`,
+/// by composing a base character with a following combining character.
+#[must_use = "the prohibition check result must be used"]
+pub fn has_any_compat_decomposable(s: &str) -> bool {
+    s.chars().any(is_compat_decomposable)
+}
+
+/// Determines if `s` is unchanged by NFKD normalization.
+///
+/// This uses the NFKD_Quick_Check property, falling back to a full
+/// decomposition only when the quick check is inconclusive.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_nfkd_stable(s: &str) -> bool {
+    is_nfkd(s)
+}
+
+/// Determines if `c` is a precomposed Hangul syllable, U+AC00 to U+D7A3.
+///
+/// Normalization decomposes these algorithmically into conjoining jamo, and
+/// NFKC recomposes them again.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_hangul_syllable(c: char) -> bool {
+    matches!(c, '\u{AC00}'..='\u{D7A3}')
+}
+
+/// Determines if `c` is a conjoining Hangul jamo, i.e. is in the Hangul Jamo,
+/// Hangul Jamo Extended-A or Hangul Jamo Extended-B blocks.
+///
+/// Sequences of these are composed into Hangul syllables by NFKC.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_hangul_jamo(c: char) -> bool {
+    matches!(c, '\u{1100}'..='\u{11FF}' | '\u{A960}'..='\u{A97F}' | '\u{D7B0}'..='\u{D7FF}')
+}
+
+/// Determines if `c` is in the Hangul Compatibility Jamo block, U+3130 to
+/// U+318F.
+///
+/// These have compatibility decompositions to conjoining jamo, so NFKC maps
+/// them to those, or to Hangul syllables when several are adjacent.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_hangul_compatibility_jamo(c: char) -> bool {
+    matches!(c, '\u{3130}'..='\u{318F}')
+}
+
+/// Maps a character according to the IDNA2008 mapping of [RFC 5895][].
+///
+/// Fullwidth and halfwidth characters are mapped to their decomposition, and
+/// uppercase characters to lowercase. Returns `None` if `c` is unchanged or if
+/// its lowercase mapping is more than one character; use `idna_map_string` to
+/// handle those.
+///
+/// [RFC 5895]: https://tools.ietf.org/html/rfc5895
+#[must_use]
+pub fn idna_map_char(c: char) -> Option<char> {
+    let w = idna_width_map(c).unwrap_or(c);
+    let mut lower = w.to_lowercase();
+    match (lower.next(), lower.next()) {
+        (Some(l), None) if l != c => Some(l),
+        _ => None,
+    }
+}
+
+/// Maps a string according to the IDNA2008 mapping of [RFC 5895][].
+///
+/// Unlike the table B.2 case folding, the result is normalized with NFC rather
+/// than NFKC.
+///
+/// [RFC 5895]: https://tools.ietf.org/html/rfc5895
+#[must_use]
+pub fn idna_map_string(s: &str) -> Cow<'_, str> {
+    let mapped = s
+        .chars()
+        .flat_map(|c| idna_width_map(c).unwrap_or(c).to_lowercase())
+        .nfc()
+        .collect::<String>();
+    if mapped == s {
+        Cow::Borrowed(s)
+    } else {
+        Cow::Owned(mapped)
+    }
+}
+
+// RFC 5895, 2. Fullwidth and halfwidth characters
+fn idna_width_map(c: char) -> Option<char> {
+    if !matches!(c, '\u{3000}' | '\u{FF01}'..='\u{FFEE}') {
+        return None;
+    }
+
+    let mut decomposed = None;
+    let mut count = 0;
+    decompose_compatible(c, |d| {
+        decomposed = Some(d);
+        count += 1;
+    });
+    if count == 1 {
+        decomposed
+    } else {
+        None
+    }
+}
+
+/// Determines if `c` separates the labels of a domain name, as described in
+/// [RFC 3490, Section 3.1][].
+///
+/// [RFC 3490, Section 3.1]: https://tools.ietf.org/html/rfc3490#section-3.1
+#[must_use = "the prohibition check result must be used"]
+pub fn is_idna_label_separator(c: char) -> bool {
+    matches!(c, '\u{002E}' | '\u{3002}' | '\u{FF0E}' | '\u{FF61}')
+}
+
+/// Determines if `c` is an ASCII letter, digit or hyphen, the characters
+/// permitted in a traditional DNS label.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_ascii_label_char(c: char) -> bool {
+    matches!(c, 'a'..='z' | 'A'..='Z' | '0'..='9' | '-')
+}
+
+/// The maximum length in bytes of a DNS label, from [RFC 1035][].
+///
+/// [RFC 1035]: https://tools.ietf.org/html/rfc1035
+pub const MAX_LABEL_BYTES: usize = 63;
+
+/// The maximum length in bytes of a DNS domain name, from [RFC 1035][].
+///
+/// [RFC 1035]: https://tools.ietf.org/html/rfc1035
+pub const MAX_DOMAIN_NAME_BYTES: usize = 255;
+
+/// Determines if `label` is at most `max_bytes` bytes long.
+///
+/// DNS limits labels to `MAX_LABEL_BYTES` bytes and whole domain names to
+/// `MAX_DOMAIN_NAME_BYTES`. The limits apply to the ASCII Compatible Encoding
+/// of a label, so they should be checked after Punycode encoding.
+#[must_use = "the prohibition check result must be used"]
+pub fn check_label_max_bytes(label: &str, max_bytes: usize) -> bool {
+    label.len() <= max_bytes
+}
+
+/// Determines if every character of `s` is an ASCII letter, digit or hyphen.
+#[must_use = "the prohibition check result must be used"]
+pub fn is_all_ascii_label_chars(s: &str) -> bool {
+    s.bytes()
+        .all(|b| matches!(b, b'a'..=b'z' | b'A'..=b'Z' | b'0'..=b'9' | b'-'))
+}
+
+/// Returns an error for the first A.1 unassigned code point in `s`.
+///
+/// RFC 3454, Section 7 requires that stored strings not contain unassigned
+/// code points. This check is applied by `saslprep_stored` (and the deprecated
+/// `saslprep`), `nameprep`, `nodeprep`, `resourceprep` and `prepare` with the
+/// standard profiles, but not by `saslprep_query` or by `PrepOptions` after
+/// `without_unassigned_check`. The PRECIS profiles, `nicknameprep` and
+/// `opaque_string`, use the unassigned code points of the current version of
+/// Unicode instead.
+#[must_use = "the check result must be used"]
+pub fn check_no_unassigned(s: &str) -> Result<(), Error> {
+    match s.chars().find(|&c| unassigned_code_point(c)) {
+        Some(c) => Err(Error(ErrorKind::UnassignedCodePoint(c), None)),
+        None => Ok(()),
+    }
+}
+
+/// Returns an error if `s` is empty.
+///
+/// Several profiles require that the prepared string is not empty.
+#[must_use = "the check result must be used"]
+pub fn check_non_empty(s: &str) -> Result<(), Error> {
+    if s.is_empty() {
+        return Err(Error(ErrorKind::EmptyString, None));
+    }
+    Ok(())
+}
+
+/// Returns an error if `s` consists entirely of space characters.
+///
+/// Both C.1.1 and C.1.2 space characters are considered. An empty string is
+/// not rejected; use `check_non_empty` for that.
+#[must_use = "the check result must be used"]
+pub fn check_not_all_spaces(s: &str) -> Result<(), Error> {
+    if !s.is_empty() && s.chars().all(is_white_space) {
+        return Err(Error(ErrorKind::AllSpaces, None));
+    }
+    Ok(())
+}
+
+/// Determines if `c` has the derived property value PVALID as defined in
+/// [RFC 5892, Section 3][], i.e. is permitted in an IDNA2008 domain name label
+/// without contextual rules.
+///
+/// General categories are taken from the current version of Unicode. Case
+/// folding is approximated with the full lowercase mapping of the full
+/// uppercase mapping, which agrees with Unicode case folding for the purposes
+/// of the stability check.
+///
+/// [RFC 5892, Section 3]: https://tools.ietf.org/html/rfc5892#section-3
+#[must_use = "the prohibition check result must be used"]
+pub fn is_pvalid_for_dns(c: char) -> bool {
+    // F. Exceptions
+    match c {
+        '\u{00DF}' | '\u{03C2}' | '\u{06FD}' | '\u{06FE}' | '\u{0F0B}' | '\u{3007}' => return true,
+        '\u{0640}'
+        | '\u{07FA}'
+        | '\u{302E}'
+        | '\u{302F}'
+        | '\u{3031}'..='\u{3035}'
+        | '\u{303B}' => return false,
+        _ if is_contexto(c) => return false,
+        _ => {}
+    }
+
+    // J. Unassigned
+    if c.general_category() == GeneralCategory::Unassigned {
+        return false;
+    }
+
+    // K. LDH
+    if matches!(c, 'a'..='z' | '0'..='9' | '-') {
+        return true;
+    }
+
+    // H. JoinControl
+    if is_contextj(c) {
+        return false;
+    }
+
+    // B. Unstable
+    let nfkc = iter::once(c).nfkc().collect::<String>();
+    let folded = nfkc.chars().flat_map(idna_case_fold).nfkc();
+    if !folded.eq(iter::once(c)) {
+        return false;
+    }
+
+    // C. IgnorableProperties
+    if is_default_ignorable(c) || c.is_whitespace() || non_character_code_point(c) {
+        return false;
+    }
+
+    // D. IgnorableBlocks
+    if matches!(c, '\u{20D0}'..='\u{20FF}' | '\u{1D100}'..='\u{1D24F}') {
+        return false;
+    }
+
+    // I. OldHangulJamo
+    if matches!(c, '\u{1100}'..='\u{11FF}'
+        | '\u{A960}'..='\u{A97C}'
+        | '\u{D7B0}'..='\u{D7C6}'
+        | '\u{D7CB}'..='\u{D7FB}')
+    {
+        return false;
+    }
+
+    // A. LetterDigits
+    matches!(
+        c.general_category(),
+        GeneralCategory::LowercaseLetter
+            | GeneralCategory::UppercaseLetter
+            | GeneralCategory::OtherLetter
+            | GeneralCategory::DecimalNumber
+            | GeneralCategory::ModifierLetter
+            | GeneralCategory::NonspacingMark
+            | GeneralCategory::SpacingMark
+    )
+}
+
+/// Determines if `c` has the derived property value CONTEXTJ as defined in
+/// [RFC 5892][], i.e. is a join control character.
+///
+/// [RFC 5892]: https://tools.ietf.org/html/rfc5892
+#[must_use = "the prohibition check result must be used"]
+pub fn is_contextj(c: char) -> bool {
+    matches!(c, '\u{200C}' | '\u{200D}')
+}
+
+/// Determines if `c` has the derived property value CONTEXTO as defined in
+/// [RFC 5892][], i.e. has a contextual rule in Appendix A.3 to A.9.
+///
+/// [RFC 5892]: https://tools.ietf.org/html/rfc5892
+#[must_use = "the prohibition check result must be used"]
+pub fn is_contexto(c: char) -> bool {
+    matches!(c, '\u{00B7}'
+        | '\u{0375}'
+        | '\u{05F3}'
+        | '\u{05F4}'
+        | '\u{30FB}'
+        | '\u{0660}'..='\u{0669}'
+        | '\u{06F0}'..='\u{06F9}')
+}
+
+fn idna_case_fold(c: char) -> Vec<char> {
+    // Cherokee case folds to uppercase, for compatibility with its encoding
+    // in earlier versions of Unicode.
+    if matches!(c, '\u{13A0}'..='\u{13FD}' | '\u{AB70}'..='\u{ABBF}') {
+        return c.to_uppercase().collect();
+    }
+    c.to_uppercase().flat_map(char::to_lowercase).collect()
+}
+
+/// Determines if `c` is DISALLOWED or UNASSIGNED in the PRECIS
+/// FreeformClass, as defined in [RFC 8264, Section 9][].
+///
+/// Characters with the derived property value CONTEXTJ or CONTEXTO are not
+/// prohibited by this function; their contextual rules are checked by
+/// `check_contextual_rules`. General categories are taken from the current
+/// version of Unicode.
+///
+/// [RFC 8264, Section 9]: https://tools.ietf.org/html/rfc8264#section-9
+#[must_use = "the prohibition check result must be used"]
+pub fn precis_freeform_prohibited(c: char) -> bool {
+    // 9.1. Exceptions
+    match c {
+        '\u{00DF}' | '\u{03C2}' | '\u{06FD}' | '\u{06FE}' | '\u{0F0B}' | '\u{3007}' => {
+            return false
+        }
+        '\u{0640}'
+        | '\u{07FA}'
+        | '\u{302E}'
+        | '\u{302F}'
+        | '\u{3031}'..='\u{3035}'
+        | '\u{303B}' => return true,
+        _ if is_contexto(c) => return false,
+        _ => {}
+    }
+
+    // 9.3. Unassigned
+    if c.general_category() == GeneralCategory::Unassigned {
+        return true;
+    }
+
+    // 9.4. ASCII7
+    if matches!(c, '\u{0021}'..='\u{007E}') {
+        return false;
+    }
+
+    // 9.5. JoinControl
+    if is_contextj(c) {
+        return false;
+    }
+
+    // 9.6. OldHangulJamo
+    if matches!(c, '\u{1100}'..='\u{11FF}'
+        | '\u{A960}'..='\u{A97C}'
+        | '\u{D7B0}'..='\u{D7C6}'
+        | '\u{D7CB}'..='\u{D7FB}')
+    {
+        return true;
+    }
+
+    // 9.7. PrecisIgnorableProperties
+    if is_default_ignorable(c) || non_character_code_point(c) {
+        return true;
+    }
+
+    // 9.8. Controls
+    if c.general_category() == GeneralCategory::Control {
+        return true;
+    }
+
+    // 9.9. HasCompat, which is allowed in the FreeformClass
+    if !iter::once(c).nfkc().eq(iter::once(c)) {
+        return false;
+    }
+
+    // 9.10. LetterDigits, 9.11. OtherLetterDigits, 9.12. Spaces,
+    // 9.13. Symbols and 9.14. Punctuation
+    let allowed = matches!(
+        c.general_category_group(),
+        GeneralCategoryGroup::Letter
+            | GeneralCategoryGroup::Mark
+            | GeneralCategoryGroup::Number
+            | GeneralCategoryGroup::Symbol
+            | GeneralCategoryGroup::Punctuation
+    ) || is_unicode_zs_category(c);
+    !allowed
+}
+
+/// Checks the contextual rules of [RFC 5892, Appendix A][] for the CONTEXTJ
+/// and CONTEXTO characters in `s`.
+///
+/// An error naming the first character whose rule is not satisfied is
+/// returned. The rule for U+200C ZERO WIDTH NON-JOINER is only satisfied
+/// after a virama, since the joining types it otherwise depends on are not
+/// available. Scripts are approximated by the Unicode blocks of the Greek,
+/// Hebrew, Hiragana, Katakana and CJK characters.
+///
+/// [RFC 5892, Appendix A]: https://tools.ietf.org/html/rfc5892#appendix-A
+#[must_use = "the check result must be used"]
+pub fn check_contextual_rules(s: &str) -> Result<(), Error> {
+    let mut before = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        let after = chars.clone().next();
+        let valid = match c {
+            // A.1. ZERO WIDTH NON-JOINER and A.2. ZERO WIDTH JOINER
+            '\u{200C}' | '\u{200D}' => {
+                matches!(before, Some(b) if canonical_combining_class(b) == 9)
+            }
+            // A.3. MIDDLE DOT
+            '\u{00B7}' => before == Some('l') && after == Some('l'),
+            // A.4. GREEK LOWER NUMERAL SIGN (KERAIA)
+            '\u{0375}' => matches!(after, Some(a) if is_greek_block(a)),
+            // A.5. HEBREW PUNCTUATION GERESH and A.6. GERSHAYIM
+            '\u{05F3}' | '\u{05F4}' => matches!(before, Some(b) if is_hebrew_block(b)),
+            // A.7. KATAKANA MIDDLE DOT
+            '\u{30FB}' => s.chars().any(is_hiragana_katakana_or_han_block),
+            // A.8. ARABIC-INDIC DIGITS
+            '\u{0660}'..='\u{0669}' => !s.contains(|c| matches!(c, '\u{06F0}'..='\u{06F9}')),
+            // A.9. EXTENDED ARABIC-INDIC DIGITS
+            '\u{06F0}'..='\u{06F9}' => !s.contains(|c| matches!(c, '\u{0660}'..='\u{0669}')),
+            _ => true,
+        };
+        if !valid {
+            return Err(Error::prohibited(c, None));
+        }
+        before = Some(c);
+    }
+    Ok(())
+}
+
+fn is_greek_block(c: char) -> bool {
+    // the Coptic letters of the Greek and Coptic block are excluded
+    matches!(c, '\u{0370}'..='\u{03E1}' | '\u{03F0}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}')
+}
+
+fn is_hebrew_block(c: char) -> bool {
+    matches!(c, '\u{0591}'..='\u{05F4}' | '\u{FB1D}'..='\u{FB4F}')
+}
+
+fn is_hiragana_katakana_or_han_block(c: char) -> bool {
+    // U+30FB itself and U+30FC are in the Common script
+    matches!(c, '\u{2E80}'..='\u{2FDF}'
+        | '\u{3005}'
+        | '\u{3007}'
+        | '\u{3021}'..='\u{3029}'
+        | '\u{3038}'..='\u{303B}'
+        | '\u{3041}'..='\u{30FA}'
+        | '\u{30FD}'..='\u{30FF}'
+        | '\u{31F0}'..='\u{31FF}'
+        | '\u{3400}'..='\u{4DBF}'
+        | '\u{4E00}'..='\u{9FFF}'
+        | '\u{F900}'..='\u{FAFF}'
+        | '\u{FF66}'..='\u{FF6F}'
+        | '\u{FF71}'..='\u{FF9D}'
+        | '\u{1B000}'..='\u{1B16F}'
+        | '\u{20000}'..='\u{3134F}')
+}
+
+/// Determines if `c` has the Unicode Default_Ignorable_Code_Point property,
+/// which [RFC 8264][] makes DISALLOWED in PRECIS string classes.
+///
+/// Most of these characters are also handled by the RFC 3454 tables: the soft
+/// hyphen, joiners, variation selectors and U+FEFF are in B.1, the remaining
+/// invisible format controls are in C.2.2 or C.8, the tag characters are in
+/// C.9, and those added after Unicode 3.2 are unassigned in A.1. Only the
+/// Hangul fillers U+115F, U+1160, U+3164 and U+FFA0, and the Khmer inherent
+/// vowels U+17B4 and U+17B5, are allowed by the stringprep profiles.
+///
+/// The ranges are those of DerivedCoreProperties.txt in Unicode 17.0.0.
+///
+/// [RFC 8264]: https://tools.ietf.org/html/rfc8264
+#[must_use = "the prohibition check result must be used"]
+pub fn is_default_ignorable(c: char) -> bool {
+    matches!(c, '\u{00AD}'
+        | '\u{034F}'
+        | '\u{061C}'
+        | '\u{115F}'..='\u{1160}'
+        | '\u{17B4}'..='\u{17B5}'
+        | '\u{180B}'..='\u{180F}'
+        | '\u{200B}'..='\u{200F}'
+        | '\u{202A}'..='\u{202E}'
+        | '\u{2060}'..='\u{206F}'
+        | '\u{3164}'
+        | '\u{FE00}'..='\u{FE0F}'
+        | '\u{FEFF}'
+        | '\u{FFA0}'
+        | '\u{FFF0}'..='\u{FFF8}'
+        | '\u{1BCA0}'..='\u{1BCA3}'
+        | '\u{1D173}'..='\u{1D17A}'
+        | '\u{E0000}'..='\u{E0FFF}')
+}
+
+/// Determines if `c` may appear in an IRI as defined in [RFC 3987][].
+///
+/// Spaces, control characters, private use and non-character code points are
+/// not allowed, nor are code points unassigned in Unicode 3.2.
+///
+/// [RFC 3987]: https://tools.ietf.org/html/rfc3987
+#[must_use = "the prohibition check result must be used"]
+pub fn is_iri_allowed(c: char) -> bool {
+    !(unassigned_code_point(c)
+        || ascii_space_character(c)
+        || non_ascii_space_character(c)
+        || ascii_control_character(c)
+        || non_ascii_control_character(c)
+        || private_use(c)
+        || non_character_code_point(c))
+}
+
 /// Determines if `c` is to be removed according to section 7.2 of
 /// [ITU-T Recommendation X.520 (2019)](https://www.itu.int/rec/T-REC-X.520-201910-I/en).
+#[must_use = "the prohibition check result must be used"]
 pub fn x520_mapped_to_nothing(c: char) -> bool {
     match c {
         '\u{00AD}'
@@ -243,6 +1497,7 @@ pub fn x520_mapped_to_nothing(c: char) -> bool {
 
 /// Determines if `c` is to be replaced by SPACE (0x20) according to section 7.2 of
 /// [ITU-T Recommendation X.520 (2019)](https://www.itu.int/rec/T-REC-X.520-201910-I/en).
+#[must_use = "the prohibition check result must be used"]
 pub fn x520_mapped_to_space(c: char) -> bool {
     match c {
         '\u{09}' | '\u{0A}'..='\u{0D}' | '\u{85}' => true,
@@ -313,3 +313,19 @@ fn should_expand() {
     let output = "xss\u{30ad}\u{30ed}\u{30e1}\u{30fc}\u{30c8}\u{30eb}\u{0069}\u{0307}\u{0074}\u{0065}\u{006c}\u{0028}\u{0064}\u{0029}\u{30a2}\u{30d1}\u{30fc}\u{30c8}";
     assert_eq!(output, nameprep(input).unwrap());
 }
+
+// Case folding uses table B.2 rather than the current Unicode lowercase mapping
+#[test]
+fn should_case_fold_with_table_b2() {
+    assert_eq!("i\u{0307}", nameprep("\u{0130}").unwrap());
+    for &(input, output) in &[
+        ("\u{00df}", "ss"),
+        ("\u{03c2}", "\u{03c3}"),
+        ("\u{00b5}", "\u{03bc}"),
+        ("\u{03d0}", "\u{03b2}"),
+        ("\u{1e9b}", "\u{1e61}"),
+    ] {
+        assert_ne!(output, input.to_lowercase());
+        assert_eq!(output, nameprep(input).unwrap());
+    }
+}
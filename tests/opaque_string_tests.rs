@@ -0,0 +1,89 @@
+// The OpaqueString examples from RFC 8265, Section 4.2.3
+extern crate stringprep;
+
+use std::borrow::Cow;
+use stringprep::{opaque_string, ErrorKind};
+
+#[test]
+fn ascii_space_is_allowed() {
+    assert_eq!(
+        opaque_string("correct horse battery staple").unwrap(),
+        "correct horse battery staple"
+    );
+}
+
+#[test]
+fn case_is_preserved() {
+    assert_eq!(
+        opaque_string("Correct Horse Battery Staple").unwrap(),
+        "Correct Horse Battery Staple"
+    );
+}
+
+#[test]
+fn non_ascii_letters_are_allowed() {
+    assert_eq!(
+        opaque_string("\u{03C0}\u{00DF}\u{00E5}").unwrap(),
+        "\u{03C0}\u{00DF}\u{00E5}"
+    );
+}
+
+#[test]
+fn symbols_are_allowed() {
+    assert_eq!(
+        opaque_string("Jack of \u{2666}s").unwrap(),
+        "Jack of \u{2666}s"
+    );
+}
+
+#[test]
+fn non_ascii_space_is_mapped() {
+    assert_eq!(opaque_string("foo\u{1680}bar").unwrap(), "foo bar");
+}
+
+#[test]
+fn empty_string_is_rejected() {
+    assert_eq!(
+        opaque_string("").unwrap_err().kind(),
+        &ErrorKind::EmptyString
+    );
+}
+
+#[test]
+fn control_character_is_rejected() {
+    assert_eq!(
+        opaque_string("my cat is a \u{0009}by").unwrap_err().kind(),
+        &ErrorKind::ProhibitedCharacter('\u{0009}')
+    );
+}
+
+#[test]
+fn rejection_does_not_name_an_rfc3454_table() {
+    assert_eq!(
+        opaque_string("a\u{0007}").unwrap_err().to_string(),
+        "prohibited character `\u{0007}`"
+    );
+    assert_eq!(
+        opaque_string("a\u{200D}b").unwrap_err().to_string(),
+        "prohibited character `\u{200D}`"
+    );
+}
+
+#[test]
+fn prepared_strings_are_borrowed() {
+    assert!(matches!(
+        opaque_string("I\u{00E9}X").unwrap(),
+        Cow::Borrowed(_)
+    ));
+    assert!(matches!(
+        opaque_string("Ie\u{0301}X").unwrap(),
+        Cow::Owned(ref s) if s == "I\u{00E9}X"
+    ));
+}
+
+#[test]
+fn compatibility_characters_are_not_normalized() {
+    // unlike SASLprep, which normalizes these with NFKC
+    assert_eq!(opaque_string("\u{2168}").unwrap(), "\u{2168}");
+    assert_eq!(opaque_string("\u{FF21}").unwrap(), "\u{FF21}");
+}
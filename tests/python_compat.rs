@@ -0,0 +1,119 @@
+// Compares saslprep against an implementation built on Python's stringprep module.
+//
+// The test is skipped if `python3` can't be run.
+extern crate stringprep;
+
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+use stringprep::saslprep_stored;
+
+// Reads one string per line, as space separated hexadecimal code points, and
+// writes the prepared string in the same format, or `error`.
+const SCRIPT: &str = r#"
+import stringprep
+import sys
+import unicodedata
+
+PROHIBITED = (
+    stringprep.in_table_c12,
+    stringprep.in_table_c21,
+    stringprep.in_table_c22,
+    stringprep.in_table_c3,
+    stringprep.in_table_c4,
+    stringprep.in_table_c5,
+    stringprep.in_table_c6,
+    stringprep.in_table_c7,
+    stringprep.in_table_c8,
+    stringprep.in_table_c9,
+    stringprep.in_table_a1,
+)
+
+def saslprep(s):
+    s = "".join(
+        " " if stringprep.in_table_c12(c) else c
+        for c in s
+        if stringprep.in_table_c12(c) or not stringprep.in_table_b1(c)
+    )
+    s = unicodedata.ucd_3_2_0.normalize("NFKC", s)
+    if any(f(c) for c in s for f in PROHIBITED):
+        return None
+    if any(stringprep.in_table_d1(c) for c in s):
+        if any(stringprep.in_table_d2(c) for c in s):
+            return None
+        if not stringprep.in_table_d1(s[0]) or not stringprep.in_table_d1(s[-1]):
+            return None
+    return s
+
+for line in sys.stdin:
+    s = "".join(chr(int(c, 16)) for c in line.split())
+    prepared = saslprep(s)
+    if prepared is None:
+        print("error")
+    else:
+        print(" ".join("%X" % ord(c) for c in prepared))
+"#;
+
+const INPUTS: &[&str] = &[
+    "user",
+    "I\u{00AD}X",
+    "a\u{0007}",
+    "\u{007F}",
+    "\u{00A0}a\u{3000}b",
+    "a\u{200B}b",
+    "Stra\u{00DF}e",
+    "\u{2168}",
+    "\u{FB01}",
+    "\u{0627}1\u{0628}",
+    "\u{0627}a\u{0628}",
+    "\u{0627}1",
+    "\u{E000}",
+    "\u{F0000}",
+    "\u{FFFF}",
+    "\u{0221}",
+];
+
+fn encode(s: &str) -> String {
+    s.chars()
+        .map(|c| format!("{:X}", c as u32))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[test]
+fn saslprep_matches_python() {
+    let mut child = match Command::new("python3")
+        .arg("-c")
+        .arg(SCRIPT)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+    {
+        Ok(child) => child,
+        Err(e) => {
+            eprintln!("skipping, unable to run python3: {}", e);
+            return;
+        }
+    };
+
+    {
+        let stdin = child.stdin.as_mut().unwrap();
+        for input in INPUTS {
+            writeln!(stdin, "{}", encode(input)).unwrap();
+        }
+    }
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let lines = stdout.lines().collect::<Vec<_>>();
+    assert_eq!(lines.len(), INPUTS.len());
+
+    for (input, &expected) in INPUTS.iter().zip(&lines) {
+        let actual = match saslprep_stored(input) {
+            Ok(prepared) => encode(&prepared),
+            Err(_) => "error".to_string(),
+        };
+        assert_eq!(actual, expected, "input {:?}", input);
+    }
+}
@@ -1,7 +1,11 @@
 // Integration tests from https://github.com/reklatsmasters/saslprep (MIT License)
+//
+// These test the deprecated `saslprep` function, which must keep behaving as
+// it always has.
+#![allow(deprecated)]
 extern crate stringprep;
 
-use stringprep::{saslprep, Error};
+use stringprep::{saslprep, saslprep_query, saslprep_stored, Error, ErrorKind};
 
 fn assert_prohibited_character<T>(result: Result<T, Error>) {
     assert!(result.is_err());
@@ -80,3 +84,33 @@ fn randalcat_should_be_first_and_last() {
 fn should_handle_unassigned_code_points() {
     assert_prohibited_character(saslprep("a\u{0487}"));
 }
+
+#[test]
+fn stored_strings_should_reject_unassigned_code_points() {
+    assert_eq!(
+        saslprep_stored("a\u{0487}").unwrap_err().kind(),
+        &ErrorKind::UnassignedCodePoint('\u{0487}')
+    );
+    assert_eq!(
+        saslprep_stored("\u{0221}").unwrap_err().kind(),
+        &ErrorKind::UnassignedCodePoint('\u{0221}')
+    );
+}
+
+#[test]
+fn query_strings_should_allow_unassigned_code_points() {
+    assert_eq!(saslprep_query("a\u{0487}").unwrap(), "a\u{0487}");
+    assert_eq!(saslprep_query("\u{0221}").unwrap(), "\u{0221}");
+    assert_prohibited_character(saslprep_query("a\u{007F}b"));
+}
+
+#[test]
+fn saslprep_should_match_stored_strings() {
+    for s in &["user", "I\u{00AD}X", "\u{2168}", "a\u{0487}", "a\u{007F}b"] {
+        match (saslprep(s), saslprep_stored(s)) {
+            (Ok(a), Ok(b)) => assert_eq!(a, b),
+            (Err(a), Err(b)) => assert_eq!(a.kind(), b.kind()),
+            _ => panic!("{:?}", s),
+        }
+    }
+}
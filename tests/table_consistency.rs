@@ -0,0 +1,186 @@
+// Checks the character tables against the Unicode properties they are derived from.
+extern crate stringprep;
+extern crate unicode_properties;
+
+use std::char;
+
+use stringprep::tables;
+use unicode_properties::{GeneralCategory, UnicodeGeneralCategory};
+
+const RFC3454: &str = include_str!("../codegen/src/rfc3454.txt");
+
+fn all_chars() -> impl Iterator<Item = char> {
+    (0..=0x10FFFF).filter_map(char::from_u32)
+}
+
+// Parses the code point ranges of a table of RFC 3454.
+fn rfc3454_table(table: &str) -> Vec<(u32, u32)> {
+    let mut lines = RFC3454.lines().map(str::trim);
+    lines
+        .find(|line| line.contains("Start Table") && line.contains(table))
+        .unwrap();
+
+    lines
+        .take_while(|line| !line.contains("End Table"))
+        .filter(|line| !line.is_empty() && !line.starts_with("Hoffman") && !line.starts_with("RFC"))
+        .map(|line| {
+            let mut range = line.split('-').map(|c| u32::from_str_radix(c, 16).unwrap());
+            let start = range.next().unwrap();
+            (start, range.next().unwrap_or(start))
+        })
+        .collect()
+}
+
+fn in_table(table: &[(u32, u32)], c: char) -> bool {
+    table
+        .iter()
+        .any(|&(start, end)| start <= c as u32 && c as u32 <= end)
+}
+
+// Characters whose bidirectional category changed after Unicode 3.2. The
+// tables follow the current Unicode data, so these legitimately differ from
+// Tables D.1 and D.2.
+const BIDI_CHANGED_SINCE_3_2: &[(u32, u32)] = &[
+    (0x06DD, 0x06DD),
+    (0x070F, 0x070F),
+    (0x0CBF, 0x0CBF),
+    (0x0CC6, 0x0CC6),
+    (0x1734, 0x1734),
+    (0x17B4, 0x17B5),
+    (0x1885, 0x1886),
+    (0x2132, 0x2132),
+    (0x2800, 0x28FF),
+    (0x302E, 0x302F),
+    (0xFDD0, 0xFDEF),
+    (0x1D6C1, 0x1D6C1),
+    (0x1D6DB, 0x1D6DB),
+    (0x1D6FB, 0x1D6FB),
+    (0x1D715, 0x1D715),
+    (0x1D735, 0x1D735),
+    (0x1D74F, 0x1D74F),
+    (0x1D76F, 0x1D76F),
+    (0x1D789, 0x1D789),
+    (0x1D7A9, 0x1D7A9),
+    (0x1D7C3, 0x1D7C3),
+];
+
+fn bidi_changed_since_3_2(c: char) -> bool {
+    // The noncharacters at the end of each plane are also no longer "L".
+    c as u32 & 0xFFFE == 0xFFFE || in_table(BIDI_CHANGED_SINCE_3_2, c)
+}
+
+#[test]
+fn bidi_tables_match_rfc3454() {
+    let d1 = rfc3454_table("D.1");
+    let d2 = rfc3454_table("D.2");
+    assert!(!d1.is_empty() && !d2.is_empty());
+
+    for c in all_chars()
+        .filter(|&c| !tables::unassigned_code_point(c))
+        .filter(|&c| !bidi_changed_since_3_2(c))
+    {
+        assert_eq!(
+            tables::bidi_r_or_al(c),
+            in_table(&d1, c),
+            "D.1 mismatch for U+{:04X}",
+            c as u32
+        );
+        assert_eq!(
+            tables::bidi_l(c),
+            in_table(&d2, c),
+            "D.2 mismatch for U+{:04X}",
+            c as u32
+        );
+    }
+}
+
+#[test]
+fn private_use_matches_general_category() {
+    for c in all_chars() {
+        assert_eq!(
+            tables::private_use(c),
+            c.general_category() == GeneralCategory::PrivateUse,
+            "U+{:04X}",
+            c as u32
+        );
+    }
+}
+
+#[test]
+fn supplementary_format_characters_are_covered() {
+    for c in all_chars().filter(|&c| c > '\u{FFFF}') {
+        if c.general_category() == GeneralCategory::Format {
+            assert!(
+                tables::non_ascii_control_character(c)
+                    || tables::is_supplementary_formatting(c)
+                    || tables::tagging_character(c),
+                "U+{:04X}",
+                c as u32
+            );
+        }
+    }
+}
+
+#[test]
+fn space_separators_match_space_tables() {
+    for c in all_chars() {
+        assert_eq!(
+            tables::is_unicode_zs_category(c),
+            (tables::ascii_space_character(c) || tables::non_ascii_space_character(c))
+                && c != '\u{200B}',
+            "U+{:04X}",
+            c as u32
+        );
+    }
+}
+
+// The properties Default_Ignorable_Code_Point is derived from in
+// DerivedCoreProperties.txt, as listed in PropList.txt of Unicode 17.0.0.
+const OTHER_DEFAULT_IGNORABLE_CODE_POINT: &[(u32, u32)] = &[
+    (0x034F, 0x034F),
+    (0x115F, 0x1160),
+    (0x17B4, 0x17B5),
+    (0x2065, 0x2065),
+    (0x3164, 0x3164),
+    (0xFFA0, 0xFFA0),
+    (0xFFF0, 0xFFF8),
+    (0xE0000, 0xE0000),
+    (0xE0002, 0xE001F),
+    (0xE0080, 0xE00FF),
+    (0xE01F0, 0xE0FFF),
+];
+
+const VARIATION_SELECTOR: &[(u32, u32)] = &[
+    (0x180B, 0x180D),
+    (0x180F, 0x180F),
+    (0xFE00, 0xFE0F),
+    (0xE0100, 0xE01EF),
+];
+
+const PREPENDED_CONCATENATION_MARK: &[(u32, u32)] = &[
+    (0x0600, 0x0605),
+    (0x06DD, 0x06DD),
+    (0x070F, 0x070F),
+    (0x0890, 0x0891),
+    (0x08E2, 0x08E2),
+    (0x110BD, 0x110BD),
+    (0x110CD, 0x110CD),
+];
+
+#[test]
+fn default_ignorable_matches_derived_core_properties() {
+    for c in all_chars() {
+        let derived = (in_table(OTHER_DEFAULT_IGNORABLE_CODE_POINT, c)
+            || c.general_category() == GeneralCategory::Format
+            || in_table(VARIATION_SELECTOR, c))
+            && !c.is_whitespace()
+            && !matches!(c, '\u{FFF9}'..='\u{FFFB}' | '\u{13430}'..='\u{1343F}')
+            && !in_table(PREPENDED_CONCATENATION_MARK, c);
+        assert_eq!(
+            tables::is_default_ignorable(c),
+            derived,
+            "U+{:04X}",
+            c as u32
+        );
+    }
+}